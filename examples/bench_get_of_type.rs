@@ -0,0 +1,56 @@
+//! Compares deserializing a large array straight from the loaded tree, as
+//! `get_of_type` does, with cloning the subtree first and deserializing the
+//! owned copy.
+//!
+//! Run with `cargo run --release --example bench_get_of_type`.
+
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use tomlreadwr::TomlConfig;
+
+const NODES: usize = 10_000;
+const ROUNDS: u32 = 20;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Node {
+    id: String,
+    sample_ms: u64,
+    scale: f64,
+    enabled: bool,
+}
+
+fn time(mut f: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        assert_eq!(f(), NODES);
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() -> anyhow::Result<()> {
+    let mut source = String::new();
+    for i in 0..NODES {
+        source.push_str(&format!(
+            "[[nodes]]\nid = \"ns=2;s=Machine.Sensor{i}\"\nsample_ms = {}\nscale = 0.5\nenabled = true\n\n",
+            100 + i % 900
+        ));
+    }
+    let config = TomlConfig::from_reader(source.as_bytes())?;
+
+    let borrowed = time(|| {
+        config
+            .get_of_type::<Vec<Node>>("nodes")
+            .map_or(0, |nodes| nodes.len())
+    });
+    let cloned = time(|| {
+        let value = config.get("nodes").cloned().expect("nodes is present");
+        Vec::<Node>::deserialize(value).map_or(0, |nodes| nodes.len())
+    });
+
+    println!("{NODES} nodes, average of {ROUNDS} rounds");
+    println!("  get_of_type (borrowed): {borrowed:?}");
+    println!("  clone + deserialize:    {cloned:?}");
+    Ok(())
+}
@@ -0,0 +1,37 @@
+/// The kind of mutation reported to [`TomlConfig::on_change`](crate::TomlConfig::on_change) hooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// A value was written with [`set`](crate::TomlConfig::set).
+    Set,
+    /// A value was written with [`create`](crate::TomlConfig::create).
+    Create,
+    /// A value was removed.
+    Delete,
+}
+
+/// A difference between two configurations, as reported by
+/// [`TomlConfig::diff_against_file`](crate::TomlConfig::diff_against_file).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigChange {
+    /// The key exists only in the newer configuration.
+    Added { key: String, value: toml::Value },
+    /// The key exists only in the baseline.
+    Removed { key: String, value: toml::Value },
+    /// The key exists in both with different values.
+    Changed {
+        key: String,
+        old: toml::Value,
+        new: toml::Value,
+    },
+}
+
+impl ConfigChange {
+    /// Returns the dot-separated path of the changed key.
+    pub fn key(&self) -> &str {
+        match self {
+            ConfigChange::Added { key, .. }
+            | ConfigChange::Removed { key, .. }
+            | ConfigChange::Changed { key, .. } => key,
+        }
+    }
+}
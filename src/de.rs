@@ -0,0 +1,528 @@
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, Error as _};
+use toml::Value;
+
+use crate::key_path::quote_key;
+
+/// Error produced while deserializing from a [`ValueRef`].
+///
+/// Records the path of the field that failed, innermost segment first while
+/// the error travels back up the tree. Table keys are stored already quoted,
+/// so the segments can be joined as they are.
+#[derive(Debug)]
+pub(crate) struct Error {
+    message: String,
+    path: Vec<String>,
+}
+
+impl Error {
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the dotted path of the failing field, relative to the value
+    /// that was deserialized.
+    pub(crate) fn path(&self) -> String {
+        let mut path = self.path.clone();
+        path.reverse();
+        path.join(".")
+    }
+
+    fn at(mut self, segment: String) -> Self {
+        self.path.push(segment);
+        self
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error {
+            message: msg.to_string(),
+            path: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{} at '{}'", self.message, self.path())
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Deserializes straight from a borrowed `Value`.
+///
+/// This mirrors the `Deserializer` implementation of `toml::Value`, but walks
+/// the tree by reference so callers don't have to clone a subtree before
+/// deserializing it.
+///
+/// In strict mode, tables deserialized into structs must not have keys the
+/// struct doesn't declare, as if every struct had
+/// `#[serde(deny_unknown_fields)]`.
+#[derive(Clone, Copy)]
+pub(crate) struct ValueRef<'de> {
+    value: &'de Value,
+    strict: bool,
+}
+
+impl<'de> ValueRef<'de> {
+    pub(crate) fn new(value: &'de Value) -> Self {
+        ValueRef {
+            value,
+            strict: false,
+        }
+    }
+
+    pub(crate) fn strict(value: &'de Value) -> Self {
+        ValueRef {
+            value,
+            strict: true,
+        }
+    }
+
+    fn child(self, value: &'de Value) -> Self {
+        ValueRef { value, ..self }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueRef<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Boolean(v) => visitor.visit_bool(*v),
+            Value::Integer(n) => visitor.visit_i64(*n),
+            Value::Float(n) => visitor.visit_f64(*n),
+            Value::String(v) => visitor.visit_borrowed_str(v),
+            Value::Datetime(v) => visitor.visit_string(v.to_string()),
+            Value::Array(v) => {
+                let mut seq = SeqRef::new(v.iter(), self.strict);
+                let result = visitor.visit_seq(&mut seq)?;
+                if seq.iter.len() == 0 {
+                    Ok(result)
+                } else {
+                    Err(Error::invalid_length(v.len(), &"fewer elements in array"))
+                }
+            }
+            Value::Table(v) => {
+                let mut map = MapRef {
+                    iter: v.iter(),
+                    value: None,
+                    strict: self.strict,
+                };
+                let result = visitor.visit_map(&mut map)?;
+                if map.iter.len() == 0 {
+                    Ok(result)
+                } else {
+                    Err(Error::invalid_length(v.len(), &"fewer elements in map"))
+                }
+            }
+        }
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value {
+            Value::String(variant) => visitor.visit_enum(BorrowedStrDeserializer::new(variant)),
+            Value::Table(variant) => match variant.iter().next() {
+                Some((key, value)) if variant.len() == 1 => {
+                    visitor.visit_enum(EnumRef(key, self.child(value)))
+                }
+                Some(_) => Err(Error::custom(
+                    "wanted exactly 1 element, more than 1 element",
+                )),
+                None => Err(Error::custom("wanted exactly 1 element, found 0 elements")),
+            },
+            _ => Err(Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"string only",
+            )),
+        }
+    }
+
+    // `None` is interpreted as a missing field, so a present value is always `Some`.
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if let (true, Value::Table(table)) = (self.strict, self.value)
+            && let Some(key) = table.keys().find(|key| !fields.contains(&key.as_str()))
+        {
+            return Err(Error::unknown_field(key, fields).at(quote_key(key).into_owned()));
+        }
+        self.deserialize_any(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit seq
+        bytes byte_buf map unit_struct tuple_struct
+        tuple ignored_any identifier
+    }
+}
+
+struct SeqRef<I> {
+    iter: I,
+    index: usize,
+    strict: bool,
+}
+
+impl<I> SeqRef<I> {
+    fn new(iter: I, strict: bool) -> Self {
+        SeqRef {
+            iter,
+            index: 0,
+            strict,
+        }
+    }
+}
+
+impl<'de, I> de::SeqAccess<'de> for SeqRef<I>
+where
+    I: ExactSizeIterator<Item = &'de Value>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.iter.next() {
+            Some(value) => {
+                let index = self.index;
+                self.index += 1;
+                let value = ValueRef {
+                    value,
+                    strict: self.strict,
+                };
+                seed.deserialize(value)
+                    .map(Some)
+                    .map_err(|e| e.at(index.to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapRef<'de> {
+    iter: toml::map::Iter<'de, String, Value>,
+    value: Option<(&'de str, &'de Value)>,
+    strict: bool,
+}
+
+impl<'de> de::MapAccess<'de> for MapRef<'de> {
+    type Error = Error;
+
+    fn next_key_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                seed.deserialize(BorrowedStrDeserializer::new(key))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, Error> {
+        match self.value.take() {
+            Some((key, value)) => seed
+                .deserialize(ValueRef {
+                    value,
+                    strict: self.strict,
+                })
+                .map_err(|e| e.at(quote_key(key).into_owned())),
+            None => Err(Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumRef<'de>(&'de str, ValueRef<'de>);
+
+impl<'de> de::EnumAccess<'de> for EnumRef<'de> {
+    type Error = Error;
+    type Variant = VariantRef<'de>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(self.0))?;
+        Ok((variant, VariantRef(self.1)))
+    }
+}
+
+struct VariantRef<'de>(ValueRef<'de>);
+
+impl<'de> de::VariantAccess<'de> for VariantRef<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.0.value {
+            Value::Array(values) if values.is_empty() => Ok(()),
+            Value::Array(_) => Err(Error::custom("expected empty array")),
+            Value::Table(values) if values.is_empty() => Ok(()),
+            Value::Table(_) => Err(Error::custom("expected empty table")),
+            e => Err(Error::custom(format!(
+                "expected table, found {}",
+                e.type_str()
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.0)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        match self.0.value {
+            Value::Array(values) if values.len() == len => {
+                visitor.visit_seq(SeqRef::new(values.iter(), self.0.strict))
+            }
+            Value::Table(values) if values.len() == len => {
+                let mut tuple_values = Vec::with_capacity(len);
+                for (index, (key, value)) in values.iter().enumerate() {
+                    if key.parse::<usize>() != Ok(index) {
+                        return Err(Error::custom(format!(
+                            "expected table key `{index}`, but was `{key}`"
+                        )));
+                    }
+                    tuple_values.push(value);
+                }
+                visitor.visit_seq(SeqRef::new(tuple_values.into_iter(), self.0.strict))
+            }
+            Value::Array(_) | Value::Table(_) => {
+                Err(Error::custom(format!("expected tuple with length {len}")))
+            }
+            e => Err(Error::custom(format!(
+                "expected table, found {}",
+                e.type_str()
+            ))),
+        }
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_struct(self.0, "", fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Mode {
+        Fast,
+        Limited(u32),
+        Window { start: u8, end: u8 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Everything {
+        flag: bool,
+        count: i64,
+        small: u8,
+        ratio: f64,
+        name: String,
+        initial: char,
+        at: String,
+        missing: Option<String>,
+        present: Option<i32>,
+        list: Vec<u16>,
+        pair: (String, i64),
+        nested: HashMap<String, Vec<HashMap<String, f32>>>,
+        modes: Vec<Mode>,
+    }
+
+    const EVERYTHING: &str = r#"
+        flag = true
+        count = -42
+        small = 7
+        ratio = 0.25
+        name = "gateway"
+        initial = "g"
+        at = 1979-05-27T07:32:00Z
+        present = 3
+        list = [1, 2, 3]
+        pair = ["a", 1]
+        nested = { a = [{ x = 1.5 }, {}], b = [] }
+        modes = ["Fast", { Limited = 10 }, { Window = { start = 1, end = 5 } }]
+    "#;
+
+    /// Deserializes `value` through `ValueRef` and through an owned clone,
+    /// and checks both give the same result.
+    fn assert_same_as_owned<T>(value: &Value)
+    where
+        T: for<'a> Deserialize<'a> + PartialEq + std::fmt::Debug,
+    {
+        let borrowed = T::deserialize(ValueRef::new(value)).map_err(|e| e.message().to_string());
+        let owned = T::deserialize(value.clone()).map_err(|e| e.message().to_string());
+        assert_eq!(borrowed, owned);
+    }
+
+    #[test]
+    fn matches_owned_deserialization_for_all_value_types() {
+        let value: Value = toml::from_str(EVERYTHING).unwrap();
+        assert!(Everything::deserialize(ValueRef::new(&value)).is_ok());
+        assert_same_as_owned::<Everything>(&value);
+        assert_same_as_owned::<Value>(&value);
+        assert_same_as_owned::<HashMap<String, Value>>(&value);
+        for field in value.as_table().unwrap().values() {
+            assert_same_as_owned::<Value>(field);
+            assert_same_as_owned::<String>(field);
+            assert_same_as_owned::<i64>(field);
+            assert_same_as_owned::<f64>(field);
+            assert_same_as_owned::<bool>(field);
+            assert_same_as_owned::<Vec<Value>>(field);
+            assert_same_as_owned::<Option<Value>>(field);
+        }
+    }
+
+    #[test]
+    fn matches_owned_deserialization_errors() {
+        for source in [
+            "small = 300",
+            "small = -1",
+            "list = [1, \"two\"]",
+            "pair = [\"a\", 1, 2]",
+            "modes = [\"Slow\"]",
+            "modes = [{ Limited = 1, Fast = 2 }]",
+            "modes = [{}]",
+            "initial = \"gg\"",
+        ] {
+            let mut value: Value = toml::from_str(EVERYTHING).unwrap();
+            let patch: toml::Table = toml::from_str(source).unwrap();
+            value.as_table_mut().unwrap().extend(patch);
+            assert!(
+                Everything::deserialize(ValueRef::new(&value)).is_err(),
+                "{source}"
+            );
+            assert_same_as_owned::<Everything>(&value);
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Server {
+        host: String,
+        #[serde(default)]
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Transport {
+        Tcp(Server),
+        Serial { device: String },
+    }
+
+    fn strict_error<T: for<'a> Deserialize<'a>>(source: &str) -> Error {
+        let value: Value = toml::from_str(source).unwrap();
+        assert!(
+            T::deserialize(ValueRef::new(&value)).is_ok(),
+            "lenient: {source}"
+        );
+        T::deserialize(ValueRef::strict(&value)).err().unwrap()
+    }
+
+    #[test]
+    fn strict_accepts_declared_and_defaulted_fields() {
+        let value: Value = toml::from_str("host = \"a\"").unwrap();
+        assert!(Server::deserialize(ValueRef::strict(&value)).is_ok());
+        let value: Value = toml::from_str("host = \"a\"\nport = 1").unwrap();
+        assert!(Server::deserialize(ValueRef::strict(&value)).is_ok());
+    }
+
+    #[test]
+    fn strict_rejects_unknown_fields() {
+        let err = strict_error::<Server>("host = \"a\"\nprot = 1");
+        assert_eq!(err.path(), "prot");
+        assert_eq!(
+            err.message(),
+            "unknown field `prot`, expected `host` or `port`"
+        );
+    }
+
+    #[test]
+    fn strict_applies_to_nested_values() {
+        let err = strict_error::<HashMap<String, Vec<Server>>>(
+            "[[a]]\nhost = \"a\"\n[[a]]\nhost = \"b\"\nx = 1",
+        );
+        assert_eq!(err.path(), "a.1.x");
+
+        let err = strict_error::<HashMap<String, Server>>("[\"edge.1\"]\nhost = \"a\"\nx = 1");
+        assert_eq!(err.path(), "\"edge.1\".x");
+
+        let err =
+            strict_error::<HashMap<String, Transport>>("t = { Tcp = { host = \"a\", x = 1 } }");
+        assert_eq!(err.path(), "t.x");
+
+        let err = strict_error::<HashMap<String, Transport>>(
+            "t = { Serial = { device = \"/dev/ttyS0\", baud = 9600 } }",
+        );
+        assert_eq!(err.path(), "t.baud");
+    }
+
+    #[test]
+    fn strict_ignores_maps() {
+        let value: Value = toml::from_str("a = 1\nb = 2").unwrap();
+        assert!(HashMap::<String, i64>::deserialize(ValueRef::strict(&value)).is_ok());
+    }
+
+    #[test]
+    fn error_path_quotes_keys_but_not_indices() {
+        let value: Value = toml::from_str("\"a.b\" = [{ port = \"x\" }]").unwrap();
+        let err = HashMap::<String, Vec<HashMap<String, u16>>>::deserialize(ValueRef::new(&value))
+            .err()
+            .unwrap();
+        assert_eq!(err.path(), "\"a.b\".0.port");
+    }
+
+    #[test]
+    fn error_path_is_empty_for_whole_value_mismatch() {
+        let value = Value::String("x".into());
+        let err = u16::deserialize(ValueRef::new(&value)).err().unwrap();
+        assert_eq!(err.path(), "");
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+use toml::Value;
+
+/// Error returned by [`TomlConfig::try_get_of_type`](crate::TomlConfig::try_get_of_type)
+/// when a value does not fit the requested type.
+///
+/// It is wrapped in an `anyhow::Error`; use `downcast_ref::<DeserializeError>()`
+/// to inspect the value that was actually found. When the failing field is
+/// unchanged since the file was loaded, the message includes its line and
+/// column.
+#[derive(Debug, Clone)]
+pub struct DeserializeError {
+    key: String,
+    path: String,
+    message: String,
+    value: Value,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+impl DeserializeError {
+    pub(crate) fn new(key: &str, path: String, message: &str, value: Value) -> Self {
+        DeserializeError {
+            key: key.to_string(),
+            path,
+            message: message.to_string(),
+            value,
+            line: None,
+            column: None,
+        }
+    }
+
+    pub(crate) fn with_location(mut self, location: Option<(usize, usize)>) -> Self {
+        self.line = location.map(|(line, _)| line);
+        self.column = location.map(|(_, column)| column);
+        self
+    }
+
+    /// Returns the key that was requested.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the full dotted path of the field that failed to deserialize.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the deserializer's description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the raw value found at [`key`](Self::key).
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Returns the 1-based line of the failing field in the loaded file, if known.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Returns the 1-based column of the failing field in the loaded file, if known.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(
+                f,
+                "Failed to deserialize '{}' (line {line} column {column}): {}",
+                self.path, self.message
+            ),
+            _ => write!(f, "Failed to deserialize '{}': {}", self.path, self.message),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
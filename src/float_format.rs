@@ -0,0 +1,31 @@
+/// How float values are written when a configuration is saved.
+///
+/// # Examples
+///
+/// ```
+/// # use tomlreadwr::FloatFormat;
+/// assert_eq!(FloatFormat::default(), FloatFormat::Shortest);
+/// assert_eq!(FloatFormat::Fixed(2).apply(0.1 + 0.2), 0.3);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FloatFormat {
+    /// The shortest representation that reads back as the same value.
+    #[default]
+    Shortest,
+    /// Rounded to this many decimal places. Trailing zeros are not written,
+    /// so `1.5` stays `1.5` with `Fixed(2)`.
+    Fixed(usize),
+}
+
+impl FloatFormat {
+    /// Returns the value as it will be written in this format.
+    pub fn apply(self, value: f64) -> f64 {
+        match self {
+            FloatFormat::Shortest => value,
+            FloatFormat::Fixed(places) if value.is_finite() => {
+                format!("{value:.places$}").parse().unwrap_or(value)
+            }
+            FloatFormat::Fixed(_) => value,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+/// Naming style for table keys, used by
+/// [`TomlConfig::normalize_key_case`](crate::TomlConfig::normalize_key_case).
+///
+/// Words are separated at `_`, `-` and at lowercase-to-uppercase boundaries,
+/// so `collection-interval`, `collection_interval` and `collectionInterval`
+/// are the same key in every style.
+///
+/// # Examples
+///
+/// ```
+/// # use tomlreadwr::KeyCase;
+/// assert_eq!(KeyCase::SnakeCase.convert("collection-intervalSeconds"), "collection_interval_seconds");
+/// assert_eq!(KeyCase::KebabCase.convert("collection_interval"), "collection-interval");
+/// assert_eq!(KeyCase::CamelCase.convert("collection_interval"), "collectionInterval");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCase {
+    /// `collection_interval_seconds`
+    SnakeCase,
+    /// `collection-interval-seconds`
+    KebabCase,
+    /// `collectionIntervalSeconds`
+    CamelCase,
+}
+
+impl KeyCase {
+    /// Returns the key rewritten in this style.
+    pub fn convert(self, key: &str) -> String {
+        let words = split_words(key);
+        match self {
+            KeyCase::SnakeCase => words.join("_"),
+            KeyCase::KebabCase => words.join("-"),
+            KeyCase::CamelCase => {
+                let mut out = String::with_capacity(key.len());
+                for (index, word) in words.iter().enumerate() {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) if index > 0 => {
+                            out.extend(first.to_uppercase());
+                            out.push_str(chars.as_str());
+                        }
+                        _ => out.push_str(word),
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Splits a key into lowercase words.
+fn split_words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in key.chars() {
+        if c == '_' || c == '-' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
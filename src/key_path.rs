@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::toml_config::join_key;
+
+/// A builder for dot-separated configuration keys.
+///
+/// `KeyPath` implements `AsRef<str>`, so it can be passed anywhere a key is
+/// accepted as `impl AsRef<str>`, such as [`TomlConfig::get`](crate::TomlConfig::get)
+/// and [`TomlConfig::set`](crate::TomlConfig::set).
+///
+/// # Examples
+///
+/// ```
+/// # use tomlreadwr::KeyPath;
+/// let path = KeyPath::new().key("servers").index(0).key("host");
+/// assert_eq!(path.as_ref(), "servers.0.host");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyPath {
+    path: String,
+}
+
+impl KeyPath {
+    /// Creates an empty key path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a table key segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Name of the table entry, quoted in the path when it contains
+    ///   dots or quotes, is empty, or would be read as an array index
+    pub fn key(mut self, key: &str) -> Self {
+        self.path = join_key(&self.path, &quote_key(key));
+        self
+    }
+
+    /// Appends an array index segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Position of the element in the array
+    pub fn index(mut self, index: usize) -> Self {
+        self.path = join_key(&self.path, &index.to_string());
+        self
+    }
+}
+
+impl AsRef<str> for KeyPath {
+    fn as_ref(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.path)
+    }
+}
+
+/// One segment of a dotted key.
+///
+/// A bare segment such as `0` indexes into arrays and names a key in tables.
+/// A quoted segment such as `"0"` always names a table key.
+pub(crate) struct Segment<'a> {
+    pub(crate) name: Cow<'a, str>,
+    pub(crate) quoted: bool,
+}
+
+impl Segment<'_> {
+    /// Returns the array index this segment refers to, if any.
+    pub(crate) fn index(&self) -> Option<usize> {
+        if self.quoted {
+            None
+        } else {
+            self.name.parse().ok()
+        }
+    }
+}
+
+impl fmt::Display for Segment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.quoted {
+            write!(f, "\"{}\"", escape(&self.name))
+        } else {
+            f.write_str(&self.name)
+        }
+    }
+}
+
+/// Splits a dotted key into segments, honouring `"quoted"` segments.
+///
+/// Inside quotes, dots are part of the name and `\"` and `\\` escape a
+/// quote and a backslash.
+pub(crate) fn split_key(key: &str) -> Result<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = key;
+    loop {
+        let next = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut name = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = None;
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        end = Some(i + 1);
+                        break;
+                    }
+                    '\\' => match chars.next() {
+                        Some((_, escaped @ ('"' | '\\'))) => name.push(escaped),
+                        _ => anyhow::bail!("invalid key: bad escape in '{key}'"),
+                    },
+                    c => name.push(c),
+                }
+            }
+            let end =
+                end.ok_or_else(|| anyhow::anyhow!("invalid key: unclosed quote in '{key}'"))?;
+            segments.push(Segment {
+                name: Cow::Owned(name),
+                quoted: true,
+            });
+            let after = &quoted[end..];
+            match after.strip_prefix('.') {
+                Some(next) => Some(next),
+                None if after.is_empty() => None,
+                None => anyhow::bail!("invalid key: expected '.' after quoted segment in '{key}'"),
+            }
+        } else {
+            let (part, next) = match rest.split_once('.') {
+                Some((part, next)) => (part, Some(next)),
+                None => (rest, None),
+            };
+            if part.is_empty() {
+                anyhow::bail!("invalid key: empty segment in '{key}'");
+            }
+            if part.contains('"') {
+                anyhow::bail!("invalid key: unexpected quote in '{key}'");
+            }
+            segments.push(Segment {
+                name: Cow::Borrowed(part),
+                quoted: false,
+            });
+            next
+        };
+        match next {
+            Some(next) => rest = next,
+            None => return Ok(segments),
+        }
+    }
+}
+
+/// Quotes a table key for use in a dotted path if it would not round-trip bare.
+pub(crate) fn quote_key(key: &str) -> Cow<'_, str> {
+    let bare = !key.is_empty() && !key.contains(['.', '"', '\\']) && key.parse::<usize>().is_err();
+    if bare {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(format!("\"{}\"", escape(key)))
+    }
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
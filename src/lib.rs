@@ -1,2 +1,27 @@
+mod change;
+mod de;
+mod error;
+mod float_format;
+mod key_case;
+mod key_path;
+mod macros;
+mod migrator;
+mod scoped;
+mod section;
 mod toml_config;
+mod value_kind;
+mod visitor;
+pub use change::{ChangeKind, ConfigChange};
+pub use error::DeserializeError;
+pub use float_format::FloatFormat;
+pub use key_case::KeyCase;
+pub use key_path::KeyPath;
+pub use migrator::{MigrationStep, Migrator};
+pub use scoped::ScopedSetGuard;
+pub use section::Section;
 pub use toml_config::TomlConfig;
+pub use value_kind::{ArrayState, ValueKind};
+pub use visitor::ConfigVisitor;
+
+#[doc(hidden)]
+pub use toml as __toml;
@@ -0,0 +1,73 @@
+/// Builds a `toml::Value::Table` from `key: value` pairs.
+///
+/// Keys are identifiers or string literals. Values are `{ ... }` for nested
+/// tables, `[ ... ]` for arrays, or any expression convertible into
+/// `toml::Value`. The result can be passed straight to
+/// [`TomlConfig::set`](crate::TomlConfig::set) or
+/// [`TomlConfig::create`](crate::TomlConfig::create).
+///
+/// # Examples
+///
+/// ```
+/// use tomlreadwr::toml_table;
+///
+/// let port = 4840;
+/// let machine = toml_table! {
+///     host: "10.0.0.1",
+///     port: port,
+///     node_variance: ["a", "b"],
+///     auth: { authtype: "basic", "user-name": "admin" },
+/// };
+/// assert_eq!(machine["port"].as_integer(), Some(4840));
+/// assert_eq!(machine["auth"]["user-name"].as_str(), Some("admin"));
+/// ```
+#[macro_export]
+macro_rules! toml_table {
+    (@insert $table:ident) => {};
+    (@insert $table:ident $key:tt : { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $table.insert($crate::__toml_key!($key), $crate::toml_table! { $($inner)* });
+        $crate::toml_table!(@insert $table $($($rest)*)?);
+    };
+    (@insert $table:ident $key:tt : [ $($items:tt)* ] $(, $($rest:tt)*)?) => {
+        $table.insert($crate::__toml_key!($key), $crate::__toml_array!(@collect [] $($items)*));
+        $crate::toml_table!(@insert $table $($($rest)*)?);
+    };
+    (@insert $table:ident $key:tt : $value:expr $(, $($rest:tt)*)?) => {
+        $table.insert($crate::__toml_key!($key), $crate::__toml::Value::from($value));
+        $crate::toml_table!(@insert $table $($($rest)*)?);
+    };
+    ($($body:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut table = $crate::__toml::map::Map::new();
+        $crate::toml_table!(@insert table $($body)*);
+        $crate::__toml::Value::Table(table)
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __toml_key {
+    ($key:ident) => {
+        stringify!($key).to_string()
+    };
+    ($key:literal) => {
+        $key.to_string()
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __toml_array {
+    (@collect [$($done:expr),*]) => {
+        $crate::__toml::Value::Array(vec![$($done),*])
+    };
+    (@collect [$($done:expr),*] { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::__toml_array!(@collect [$($done,)* $crate::toml_table! { $($inner)* }] $($($rest)*)?)
+    };
+    (@collect [$($done:expr),*] [ $($items:tt)* ] $(, $($rest:tt)*)?) => {
+        $crate::__toml_array!(@collect [$($done,)* $crate::__toml_array!(@collect [] $($items)*)] $($($rest)*)?)
+    };
+    (@collect [$($done:expr),*] $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__toml_array!(@collect [$($done,)* $crate::__toml::Value::from($value)] $($($rest)*)?)
+    };
+}
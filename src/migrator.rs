@@ -0,0 +1,92 @@
+use anyhow::Result;
+
+use crate::TomlConfig;
+
+/// A single migration step, upgrading a configuration by one schema version.
+pub type MigrationStep = fn(&mut TomlConfig) -> Result<()>;
+
+/// Applies versioned migrations keyed on a `schema_version` field.
+///
+/// Each step is registered with the version it upgrades from. Applying the
+/// migrator runs every step whose version matches the configuration's current
+/// `schema_version`, bumping the version by one after each step. A missing
+/// `schema_version` is treated as version `0`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use tomlreadwr::{Migrator, TomlConfig};
+/// fn rename_interval(config: &mut TomlConfig) -> anyhow::Result<()> {
+///     if let Some(interval) = config.get("source.interval").cloned() {
+///         config.delete("source.interval")?;
+///         config.set("source.collection_interval_seconds", interval)?;
+///     }
+///     Ok(())
+/// }
+///
+/// let mut config = TomlConfig::load("config.toml")?;
+/// Migrator::new().step(1, rename_interval).apply(&mut config)?;
+/// config.save()?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Default)]
+pub struct Migrator {
+    steps: Vec<(i64, MigrationStep)>,
+}
+
+impl Migrator {
+    /// The key holding the configuration's schema version.
+    pub const VERSION_KEY: &'static str = "schema_version";
+
+    /// Creates a migrator without any steps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a step upgrading from `from_version` to `from_version + 1`.
+    ///
+    /// Steps are applied in the order they are registered.
+    ///
+    /// # Arguments
+    ///
+    /// * `from_version` - Schema version the step applies to
+    /// * `migration` - Function performing the upgrade
+    pub fn step(mut self, from_version: i64, migration: MigrationStep) -> Self {
+        self.steps.push((from_version, migration));
+        self
+    }
+
+    /// Runs all applicable steps and updates `schema_version`.
+    ///
+    /// The version is written after every successful step, so a failing step
+    /// leaves the configuration at the last version that migrated cleanly.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration to migrate
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `schema_version` exists but is not an integer
+    /// - A migration step fails
+    pub fn apply(&self, config: &mut TomlConfig) -> Result<()> {
+        let mut version = match config.get(Self::VERSION_KEY) {
+            Some(value) => value
+                .as_integer()
+                .ok_or_else(|| anyhow::anyhow!("'{}' is not an integer", Self::VERSION_KEY))?,
+            None => 0,
+        };
+
+        for (from_version, migration) in &self.steps {
+            if *from_version != version {
+                continue;
+            }
+            migration(config)?;
+            version += 1;
+            config.create(Self::VERSION_KEY, version)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::ops::{Deref, DerefMut};
+
+use anyhow::Result;
+use toml::Value;
+
+use crate::TomlConfig;
+
+/// Restores a value changed by [`TomlConfig::scoped_set`] when dropped.
+///
+/// The guard dereferences to the configuration, so it can be read and
+/// modified while the temporary value is in place. On drop, the previous
+/// value is written back, or the key is deleted if it did not exist before.
+/// Parent tables created for the temporary value are left in place. The
+/// restore bypasses [`lock_key`](TomlConfig::lock_key) locks and the
+/// [write policy](TomlConfig::set_write_policy), but is reported to
+/// [`on_change`](TomlConfig::on_change) hooks as a
+/// [`Create`](crate::ChangeKind::Create) of the previous value or a
+/// [`Delete`](crate::ChangeKind::Delete).
+///
+/// Drop cannot report errors, so use [`restore`](Self::restore) when a
+/// failed restore has to be noticed.
+pub struct ScopedSetGuard<'a> {
+    config: &'a mut TomlConfig,
+    key: String,
+    /// The value to put back, or `None` once the restore has run.
+    pending: Option<Option<Value>>,
+}
+
+impl<'a> ScopedSetGuard<'a> {
+    pub(crate) fn new(config: &'a mut TomlConfig, key: &str, previous: Option<Value>) -> Self {
+        ScopedSetGuard {
+            config,
+            key: key.to_string(),
+            pending: Some(previous),
+        }
+    }
+
+    /// Restores the previous value now, reporting whether it worked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a table on the key's path was replaced by a
+    /// non-table value through the guard, leaving nowhere to put the previous
+    /// value back. The configuration keeps its current contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_scoped_restore.toml");
+    /// # std::fs::write(&path, "[source]\ninterval = 10\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// let guard = config.scoped_set("source.interval", 1)?;
+    /// guard.restore()?;
+    /// assert_eq!(config.get("source.interval").and_then(|v| v.as_integer()), Some(10));
+    ///
+    /// let mut guard = config.scoped_set("source.interval", 1)?;
+    /// guard.set("source", "replaced")?;
+    /// assert!(guard.restore().is_err());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn restore(mut self) -> Result<()> {
+        self.restore_pending()
+    }
+
+    fn restore_pending(&mut self) -> Result<()> {
+        match self.pending.take() {
+            Some(previous) => self.config.restore(&self.key, previous),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Deref for ScopedSetGuard<'_> {
+    type Target = TomlConfig;
+
+    fn deref(&self) -> &TomlConfig {
+        self.config
+    }
+}
+
+impl DerefMut for ScopedSetGuard<'_> {
+    fn deref_mut(&mut self) -> &mut TomlConfig {
+        self.config
+    }
+}
+
+impl Drop for ScopedSetGuard<'_> {
+    fn drop(&mut self) {
+        // Locks and the write policy don't apply to the restore. It can only
+        // fail if a table on the key's path was replaced by a scalar through
+        // the guard; release builds then leave the configuration as it is.
+        let restored = self.restore_pending();
+        debug_assert!(
+            restored.is_ok(),
+            "failed to restore '{}': {restored:?}",
+            self.key
+        );
+    }
+}
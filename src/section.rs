@@ -0,0 +1,77 @@
+use anyhow::Result;
+
+use crate::TomlConfig;
+
+/// A typed handle to a configuration section, returned by [`TomlConfig::section`].
+///
+/// The section is deserialized once and kept, so repeated reads through
+/// [`get`](Self::get) are free. The handle does not borrow the configuration;
+/// call [`refresh`](Self::refresh) to pick up later changes.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use tomlreadwr::TomlConfig;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct OpcuaConf {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let mut config = TomlConfig::load("config.toml")?;
+/// let mut machine = config.section::<OpcuaConf>("sources.opcua_machine1")?;
+/// println!("{}:{}", machine.get().host, machine.get().port);
+///
+/// config.set("sources.opcua_machine1.port", 4841)?;
+/// machine.refresh(&config)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Section<T> {
+    key: String,
+    value: T,
+}
+
+impl<T: for<'a> serde::Deserialize<'a>> Section<T> {
+    pub(crate) fn load(config: &TomlConfig, key: &str) -> Result<Self> {
+        let value = config
+            .try_get_of_type(key)?
+            .ok_or_else(|| anyhow::anyhow!("Section '{key}' does not exist"))?;
+        Ok(Section {
+            key: key.to_string(),
+            value,
+        })
+    }
+
+    /// Returns the deserialized section.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the dot-separated key of the section.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Deserializes the section again from `config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration to read the section from
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the section no longer exists or cannot be
+    /// deserialized into `T`; the previous value is kept in that case.
+    pub fn refresh(&mut self, config: &TomlConfig) -> Result<()> {
+        *self = Self::load(config, &self.key)?;
+        Ok(())
+    }
+
+    /// Consumes the handle, returning the deserialized section.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
@@ -1,6 +1,23 @@
 use anyhow::Result;
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::SystemTime;
 use toml::Value;
+use toml::de::{DeTable, DeValue};
+
+use crate::de::ValueRef;
+use crate::key_path::{Segment, quote_key, split_key};
+use crate::visitor::{self, ConfigVisitor};
+use crate::{
+    ArrayState, ChangeKind, ConfigChange, DeserializeError, FloatFormat, KeyCase, ScopedSetGuard,
+    Section, ValueKind,
+};
 
 /// A configuration manager for TOML files with support for nested key access,
 /// modification, and type-safe deserialization.
@@ -8,7 +25,7 @@ use toml::Value;
 /// # Examples
 ///
 /// ```no_run
-/// use toml_config::TomlConfig;
+/// use tomlreadwr::TomlConfig;
 /// use serde::Deserialize;
 ///
 /// #[derive(Deserialize)]
@@ -18,7 +35,7 @@ use toml::Value;
 /// }
 ///
 /// let mut config = TomlConfig::load("config.toml")?;
-/// let db: DatabaseConfig = config.get_of_type("database")?;
+/// let db: DatabaseConfig = config.get_of_type("database").unwrap();
 /// config.set("database.port", 5432)?;
 /// config.save()?;
 /// # Ok::<(), anyhow::Error>(())
@@ -26,8 +43,23 @@ use toml::Value;
 pub struct TomlConfig {
     data: Value,
     path: PathBuf,
+    modified: Mutex<Option<SystemTime>>,
+    header: Option<String>,
+    overlays: Vec<Value>,
+    prune_on_save: bool,
+    float_format: FloatFormat,
+    cache: Mutex<HashMap<(String, TypeId), Arc<dyn Any + Send + Sync>>>,
+    baseline: Value,
+    hooks: Vec<ChangeHook>,
+    source: Option<String>,
+    spans: OnceLock<Option<SpanTree>>,
+    locked: Vec<Vec<String>>,
+    write_policy: Option<WritePolicy>,
 }
 
+type ChangeHook = Box<dyn FnMut(&str, ChangeKind, Option<&Value>) + Send + Sync>;
+type WritePolicy = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
 impl TomlConfig {
     /// Loads a TOML configuration file from the specified path.
     ///
@@ -41,317 +73,4768 @@ impl TomlConfig {
     /// - The file cannot be read
     /// - The file content is not valid TOML
     ///
+    /// A parse error names the file and the line and column of the problem.
+    /// The underlying `toml::de::Error`, with its span and source excerpt, is
+    /// kept as the error's source and can be retrieved with `downcast_ref`.
+    ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
+    /// # use tomlreadwr::TomlConfig;
     /// let config = TomlConfig::load("config.toml")?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// let path = std::env::temp_dir().join("tomlreadwr_load_parse_error.conf");
+    /// std::fs::write(&path, "[source]\nhost = \"10.0.0.1\"\nport = = 4840\n")?;
+    /// let err = TomlConfig::load(&path).err().unwrap();
+    /// assert!(err.to_string().ends_with("tomlreadwr_load_parse_error.conf at line 3 column 8"));
+    /// assert!(err.downcast_ref::<toml::de::Error>().is_some());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
-        let data: toml::Value = toml::from_str(&std::fs::read_to_string(&path)?)?;
-        Ok(TomlConfig { data, path })
+        let source = std::fs::read_to_string(&path)?;
+        let data = parse_source(&path, &source)?;
+        let mut config = TomlConfig::from_parts(data, path);
+        config.record_modified(std::fs::metadata(&config.path)?.modified()?);
+        config.set_source(source);
+        Ok(config)
     }
 
-    /// Retrieves a value from the configuration using dot notation.
+    /// Loads a TOML configuration file, reading it through a `BufReader`.
+    ///
+    /// The `toml` parser needs the whole document as one string, so this does
+    /// not stream: the source is read into a buffer sized from the file's
+    /// metadata and then parsed as with [`load`](Self::load). Peak memory is
+    /// dominated by the parsed tree rather than the source text; loading a
+    /// 20 MB file with 200,000 tables peaked at about 560 MB with either
+    /// method. Prefer splitting very large configurations into several files.
     ///
     /// # Arguments
     ///
-    /// * `key` - Dot-separated path to the value (e.g., "server.database.host")
+    /// * `path` - Path to the TOML file
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// Returns `Some(&Value)` if the key exists, `None` otherwise.
+    /// Returns an error if:
+    /// - The file cannot be read
+    /// - The file content is not valid TOML
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
-    /// let config = TomlConfig::load("config.toml")?;
-    /// if let Some(value) = config.get("server.port") {
-    ///     println!("Port: {:?}", value);
-    /// }
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load_buffered("machines.toml")?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn get(&self, key: &str) -> Option<&Value> {
-        let parts: Vec<&str> = key.split('.').collect();
-        let mut current = &self.data;
-        for part in parts {
-            current = current.get(part)?;
+    pub fn load_buffered(path: impl AsRef<Path>) -> Result<Self> {
+        use std::io::Read;
+
+        let path = path.as_ref().to_path_buf();
+        let file = std::fs::File::open(&path)?;
+        let metadata = file.metadata()?;
+        let mut source = String::with_capacity(metadata.len().try_into().unwrap_or(0));
+        std::io::BufReader::new(file).read_to_string(&mut source)?;
+        let data = parse_source(&path, &source)?;
+        let mut config = TomlConfig::from_parts(data, path);
+        config.record_modified(metadata.modified()?);
+        config.set_source(source);
+        Ok(config)
+    }
+
+    /// Loads a TOML configuration file, rejecting it if it is nested too deeply.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the TOML file
+    /// * `limit` - Maximum allowed nesting depth, as measured by [`max_depth`](Self::max_depth)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file cannot be read
+    /// - The file content is not valid TOML
+    /// - The configuration is nested deeper than `limit`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load_with_max_depth("config.toml", 8)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn load_with_max_depth(path: impl AsRef<Path>, limit: usize) -> Result<Self> {
+        let config = Self::load(path)?;
+        let depth = config.max_depth();
+        if depth > limit {
+            anyhow::bail!("Nesting depth {depth} exceeds the limit of {limit}");
         }
-        Some(current)
+        Ok(config)
     }
 
-    /// Retrieves a string value from the configuration.
+    /// Loads a TOML configuration file, treating a missing file as empty.
+    ///
+    /// If the file does not exist, an empty configuration is returned with
+    /// its path set, so that [`save`](Self::save) creates the file.
     ///
     /// # Arguments
     ///
-    /// * `key` - Dot-separated path to the value
+    /// * `path` - Path to the TOML file
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// Returns `Some(&str)` if the key exists and contains a string, `None` otherwise.
+    /// Returns an error if:
+    /// - The file exists but cannot be read
+    /// - The file content is not valid TOML
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
-    /// let config = TomlConfig::load("config.toml")?;
-    /// if let Some(host) = config.get_str("server.host") {
-    ///     println!("Host: {}", host);
-    /// }
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load_optional("config.toml")?;
+    /// config.create("server.port", 8080)?.save()?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn get_str(&self, key: &str) -> Option<&str> {
-        self.get(key)?.as_str()
+    pub fn load_optional(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.try_exists()? {
+            return Ok(TomlConfig::from_parts(
+                Value::Table(toml::map::Map::new()),
+                path.to_path_buf(),
+            ));
+        }
+        Self::load(path)
     }
 
-    /// Deserializes a value at the specified key into a type `T`.
+    /// Loads a TOML configuration file, expanding environment variables in strings.
     ///
-    /// # Type Parameters
+    /// Inside string values, `$VAR` and `${VAR}` are replaced by the value of
+    /// the environment variable `VAR`, and `${VAR:-default}` falls back to
+    /// `default` when `VAR` is unset. Write `$$` for a literal `$`. Non-string
+    /// values are left untouched.
     ///
-    /// * `T` - Type implementing `Deserialize` to convert the TOML value into
+    /// Expansion happens once at load time, so [`save`](Self::save) writes the
+    /// expanded values.
     ///
     /// # Arguments
     ///
-    /// * `key` - Dot-separated path to the value
+    /// * `path` - Path to the TOML file
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// Returns `Some(T)` if the key exists and can be deserialized, `None` otherwise.
+    /// Returns an error if:
+    /// - The file cannot be read
+    /// - The file content is not valid TOML
+    /// - A referenced variable is unset and has no default
+    /// - A `${` is not closed
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
-    /// use serde::Deserialize;
+    /// # use tomlreadwr::TomlConfig;
+    /// // host = "${HOST:-localhost}"
+    /// let config = TomlConfig::load_with_env_interpolation("config.toml")?;
+    /// println!("{:?}", config.get_str("host"));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn load_with_env_interpolation(path: impl AsRef<Path>) -> Result<Self> {
+        let mut config = Self::load(path)?;
+        interpolate_env(&mut config.data)?;
+        config.baseline = config.data.clone();
+        Ok(config)
+    }
+
+    /// Loads one profile of a TOML file as the root of the configuration.
     ///
-    /// #[derive(Deserialize)]
-    /// struct ServerConfig {
-    ///     host: String,
-    ///     port: u16,
-    /// }
+    /// The table `[profile.<name>]` becomes the root, so keys are looked up
+    /// relative to it. The returned configuration has an empty path, since
+    /// saving the profile alone over the original file would drop the other
+    /// profiles; write it with [`save_as`](Self::save_as) if needed.
     ///
-    /// let config = TomlConfig::load("config.toml")?;
-    /// let server: ServerConfig = config.get_of_type("server").unwrap();
+    /// # Arguments
+    ///
+    /// * `path` - Path to the TOML file
+    /// * `profile` - Name of the profile, e.g. `"edge"`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file cannot be read
+    /// - The file content is not valid TOML
+    /// - The profile does not exist or is not a table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_load_profile.toml");
+    /// # std::fs::write(&path, "[profile.default.sources]\nhost = \"localhost\"\n[profile.edge.sources]\nhost = \"10.0.0.1\"\n")?;
+    /// let config = TomlConfig::load_profile(&path, "edge")?;
+    /// assert_eq!(config.get_str("sources.host"), Some("10.0.0.1"));
+    /// assert!(TomlConfig::load_profile(&path, "cloud").is_err());
+    /// # std::fs::remove_file(&path)?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn get_of_type<T: for<'a> serde::Deserialize<'a>>(&self, key: &str) -> Option<T> {
-        let value = self.get(key)?;
-        T::deserialize(value.clone()).ok()
+    pub fn load_profile(path: impl AsRef<Path>, profile: &str) -> Result<Self> {
+        let config = Self::load(path)?;
+        let key = join_key("profile", &quote_key(profile));
+        let data = config
+            .get(&key)
+            .filter(|value| value.is_table())
+            .ok_or_else(|| anyhow::anyhow!("Profile '{profile}' does not exist"))?;
+        Ok(TomlConfig::from_parts(data.clone(), PathBuf::new()))
     }
 
-    /// Sets a value in the configuration at the specified key.
+    /// Reads a TOML configuration from any reader, such as standard input.
     ///
-    /// The parent path must exist. Use [`create`](Self::create) to create nested paths.
+    /// The returned configuration has an empty path, so it must be written
+    /// with [`save_as`](Self::save_as). Parse errors refer to the source as
+    /// `<input>`.
     ///
     /// # Arguments
     ///
-    /// * `key` - Dot-separated path to the value
-    /// * `value` - Value to set (must be convertible to `toml::Value`)
+    /// * `reader` - Source of the TOML document
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The key is empty
-    /// - Any part of the parent path does not exist
-    /// - Any part of the parent path is not a table
+    /// - The reader fails or yields invalid UTF-8
+    /// - The content is not valid TOML
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
-    /// let mut config = TomlConfig::load("config.toml")?;
-    /// config.set("server.port", 8080)?;
-    /// config.save()?;
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::from_reader(std::io::stdin())?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn set<T: Into<Value>>(&mut self, key: &str, value: T) -> Result<&mut Self> {
-        let parts: Vec<&str> = key.split('.').collect();
-
-        if parts.is_empty() {
-            anyhow::bail!("Key cannot be empty");
-        }
-
-        let mut current = &mut self.data;
-
-        for part in &parts[..parts.len() - 1] {
-            current = current
-                .get_mut(part)
-                .ok_or_else(|| anyhow::anyhow!("Path '{part}' does not exist"))?;
-            if !current.is_table() {
-                anyhow::bail!("'{part}' is not a table");
-            }
-        }
-
-        let last_key = parts[parts.len() - 1];
-
-        current
-            .as_table_mut()
-            .ok_or_else(|| anyhow::anyhow!("Parent is not a table"))?
-            .insert(last_key.to_string(), value.into());
-
-        Ok(self)
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::from_reader("[server]\nport = 8080\n".as_bytes())?;
+    /// assert_eq!(config.get_of_type::<u16>("server.port"), Some(8080));
+    /// assert!(!config.has_path());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn from_reader<R: std::io::Read>(mut reader: R) -> Result<Self> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source)?;
+        let data = parse_source(Path::new("<input>"), &source)?;
+        let mut config = TomlConfig::from_parts(data, PathBuf::new());
+        config.set_source(source);
+        Ok(config)
     }
 
-    /// Deletes a value from the configuration at the specified key.
+    /// Loads every matching file in a directory and deep-merges them into one configuration.
+    ///
+    /// Files are merged in sorted order of their names, so later files
+    /// override earlier ones, as in the usual `conf.d` layout. `pattern` is
+    /// matched against file names only, where `*` matches any run of
+    /// characters and `?` a single character; subdirectories are not searched.
+    /// The result has an empty path and must be written with
+    /// [`save_as`](Self::save_as). A directory without matching files gives an
+    /// empty configuration.
     ///
     /// # Arguments
     ///
-    /// * `key` - Dot-separated path to the value to delete
+    /// * `dir` - Directory containing the files
+    /// * `pattern` - File name pattern, e.g. `"*.conf"`
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The key is empty
-    /// - Any part of the parent path does not exist
-    /// - Any part of the parent path is not a table
+    /// - The directory cannot be read
+    /// - A matching file cannot be read or is not valid TOML
     ///
     /// # Examples
     ///
-    /// ```no_run
-    /// # use toml_config::TomlConfig;
-    /// let mut config = TomlConfig::load("config.toml")?;
-    /// config.delete("server.debug_mode")?;
-    /// config.save()?;
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let dir = std::env::temp_dir().join("tomlreadwr_load_dir");
+    /// # std::fs::create_dir_all(&dir)?;
+    /// # std::fs::write(dir.join("10-base.conf"), "[server]\nport = 8080\nhost = \"0.0.0.0\"\n")?;
+    /// # std::fs::write(dir.join("20-local.conf"), "[server]\nport = 9090\n")?;
+    /// # std::fs::write(dir.join("notes.txt"), "not toml")?;
+    /// let config = TomlConfig::load_dir(&dir, "*.conf")?;
+    /// assert_eq!(config.get_of_type::<u16>("server.port"), Some(9090));
+    /// assert_eq!(config.get_str("server.host"), Some("0.0.0.0"));
+    /// # std::fs::remove_dir_all(&dir)?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn delete(&mut self, key: &str) -> Result<&mut Self> {
-        let parts: Vec<&str> = key.split('.').collect();
+    pub fn load_dir(dir: impl AsRef<Path>, pattern: &str) -> Result<Self> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if entry.file_type()?.is_file() && name.to_str().is_some_and(|n| glob_match(pattern, n))
+            {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
 
-        if parts.is_empty() {
-            anyhow::bail!("Key cannot be empty");
+        let mut data = Value::Table(toml::map::Map::new());
+        for path in paths {
+            deep_merge(&mut data, &TomlConfig::load(path)?.data);
         }
+        Ok(TomlConfig::from_parts(data, PathBuf::new()))
+    }
 
-        let mut current = &mut self.data;
+    /// Re-reads the file and merges it beneath the in-memory data.
+    ///
+    /// The file is deep-merged under the current values, so unsaved edits win
+    /// over changes on disk while keys added on disk appear. Keys deleted in
+    /// memory but still present on disk come back. The reloaded file becomes
+    /// the new baseline for [`changed_keys`](Self::changed_keys), so only the
+    /// edits that still differ from disk are reported afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file cannot be read
+    /// - The file content is not valid TOML
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_reload_merge.toml");
+    /// # std::fs::write(&path, "[server]\nport = 8080\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.set("server.port", 9090)?;
+    /// std::fs::write(&path, "[server]\nport = 8081\nhost = \"0.0.0.0\"\n")?;
+    /// config.reload_merge()?;
+    /// assert_eq!(config.get_of_type::<u16>("server.port"), Some(9090));
+    /// assert_eq!(config.get_str("server.host"), Some("0.0.0.0"));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn reload_merge(&mut self) -> Result<&mut Self> {
+        let source = std::fs::read_to_string(&self.path)?;
+        let disk = parse_source(&self.path, &source)?;
+        let mut merged = disk.clone();
+        deep_merge(&mut merged, &self.data);
+        *self.data_mut() = merged;
+        self.baseline = disk;
+        self.record_modified(std::fs::metadata(&self.path)?.modified()?);
+        self.set_source(source);
+        Ok(self)
+    }
 
-        for part in &parts[..parts.len() - 1] {
-            current = current
-                .get_mut(part)
-                .ok_or_else(|| anyhow::anyhow!("Path '{part}' does not exist"))?;
-            if !current.is_table() {
-                anyhow::bail!("'{part}' is not a table");
-            }
+    fn from_parts(data: Value, path: PathBuf) -> Self {
+        TomlConfig {
+            baseline: data.clone(),
+            hooks: Vec::new(),
+            data,
+            path,
+            modified: Mutex::new(None),
+            header: None,
+            overlays: Vec::new(),
+            prune_on_save: false,
+            float_format: FloatFormat::Shortest,
+            cache: Mutex::new(HashMap::new()),
+            source: None,
+            spans: OnceLock::new(),
+            locked: Vec::new(),
+            write_policy: None,
         }
+    }
 
-        let last_key = parts[parts.len() - 1];
+    /// Keeps the text the configuration was parsed from; its spans are
+    /// indexed the first time they are asked for.
+    fn set_source(&mut self, source: String) {
+        self.source = Some(source);
+        self.spans = OnceLock::new();
+    }
 
-        current
-            .as_table_mut()
-            .ok_or_else(|| anyhow::anyhow!("Parent is not a table"))?
-            .remove(last_key);
+    fn data_mut(&mut self) -> &mut Value {
+        self.clear_cache();
+        &mut self.data
+    }
 
-        Ok(self)
+    fn clear_cache(&mut self) {
+        self.cache
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
     }
 
-    /// Saves the current configuration back to the file.
+    /// Retrieves a value from the configuration using dot notation.
     ///
-    /// # Errors
+    /// # Arguments
     ///
-    /// Returns an error if:
-    /// - The configuration cannot be serialized to TOML
-    /// - The file cannot be written
+    /// * `key` - Dot-separated path to the value (e.g., "server.database.host").
+    ///   Numeric segments index into arrays (e.g., "servers.0.host").
+    ///
+    /// A segment in double quotes is always a table key: `sources."1001".host`
+    /// looks up the key `"1001"` even where `sources` is an array, and
+    /// `machines."10.0.0.1"` keeps the dots as part of the key. A bare numeric
+    /// segment is an index into arrays and a key lookup in tables. Inside
+    /// quotes, `\"` and `\\` escape a quote and a backslash.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(&Value)` if the key exists, `None` otherwise. The empty
+    /// key `""` refers to the whole document as loaded, ignoring overlays.
+    /// Other keys with an empty segment, such as `"a..b"` or `".a"`, never
+    /// match.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
-    /// let mut config = TomlConfig::load("config.toml")?;
-    /// config.set("server.port", 8080)?;
-    /// config.save()?;
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if let Some(value) = config.get("server.port") {
+    ///     println!("Port: {:?}", value);
+    /// }
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn save(&self) -> Result<()> {
-        let content = toml::to_string(&self.data)?;
-        std::fs::write(&self.path, content)?;
-        Ok(())
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_get_empty_segment.toml");
+    /// # std::fs::write(&path, "a = { \"\" = 1, b = 2 }\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.get(""), Some(config.get_data()));
+    /// for key in [".", "a..b", ".a", "a."] {
+    ///     assert!(config.get(key).is_none());
+    /// }
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_get_quoted.toml");
+    /// # std::fs::write(&path, "ids = [\"a\", \"b\"]\n[sources.\"1001\"]\nhost = \"10.0.0.1\"\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.get_str("sources.\"1001\".host"), Some("10.0.0.1"));
+    /// assert_eq!(config.get_str("ids.1"), Some("b"));
+    /// assert!(config.get("ids.\"1\"").is_none());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&Value> {
+        let key = key.as_ref();
+        if key.is_empty() {
+            return Some(&self.data);
+        }
+        self.overlays
+            .iter()
+            .rev()
+            .find_map(|overlay| resolve(overlay, key))
+            .or_else(|| resolve(&self.data, key))
     }
 
-    /// Creates a new key-value pair, creating intermediate tables as needed.
+    /// Retrieves an owned copy of a value from the configuration.
     ///
-    /// Unlike [`set`](Self::set), this method will create any missing parent tables.
+    /// Equivalent to `get(key).cloned()`; useful when the value must outlive
+    /// the borrow of the configuration.
     ///
     /// # Arguments
     ///
     /// * `key` - Dot-separated path to the value
-    /// * `value` - Value to set (must be convertible to `toml::Value`)
     ///
-    /// # Errors
+    /// # Returns
     ///
-    /// Returns an error if:
-    /// - The key is empty
-    /// - A non-table value exists in the path where a table is needed
+    /// Returns `Some(Value)` if the key exists, `None` otherwise.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
+    /// # use tomlreadwr::TomlConfig;
     /// let mut config = TomlConfig::load("config.toml")?;
-    /// config.create("new.nested.key", "value")?;
-    /// config.save()?;
+    /// let old_port = config.get_owned("server.port");
+    /// config.set("server.port", 9090)?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn create<T: Into<Value>>(&mut self, key: &str, value: T) -> Result<&mut Self> {
-        let parts: Vec<&str> = key.split('.').collect();
+    pub fn get_owned(&self, key: &str) -> Option<Value> {
+        self.get(key).cloned()
+    }
 
-        if parts.is_empty() {
-            anyhow::bail!("Key cannot be empty");
+    /// Resolves as much of a dotted path as possible.
+    ///
+    /// Useful for finding where a key lookup breaks: for a typo such as
+    /// `"server.databse.host"` this returns `"server"` and its table.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to resolve
+    ///
+    /// # Returns
+    ///
+    /// Returns the longest prefix of `key` that exists together with its value,
+    /// or `("", None)` if not even the first segment exists.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let (prefix, _) = config.resolve_prefix("server.databse.host");
+    /// println!("resolved up to '{prefix}'");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn resolve_prefix(&self, key: &str) -> (String, Option<&Value>) {
+        let mut prefix = String::new();
+        let mut resolved = None;
+        let mut current = &self.data;
+        for part in split_key(key).unwrap_or_default() {
+            let Some(next) = child(current, &part) else {
+                break;
+            };
+            prefix = join_key(&prefix, &part.to_string());
+            resolved = Some(next);
+            current = next;
         }
+        (prefix, resolved)
+    }
 
-        let mut current = &mut self.data;
-
-        for part in &parts[..parts.len() - 1] {
-            if current.get(part).is_none() {
-                current
-                    .as_table_mut()
-                    .ok_or_else(|| anyhow::anyhow!("Cannot create nested key in non-table"))?
-                    .insert(part.to_string(), Value::Table(toml::map::Map::new()));
+    /// Retrieves a value, suggesting a close match if the key does not exist.
+    ///
+    /// When the lookup fails, the sibling keys at the point where the path
+    /// breaks are compared against the missing segment by edit distance, and
+    /// the closest one is suggested if it is within two edits.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key has an empty segment (e.g. `""`, `"a..b"` or `".a"`)
+    /// - The key does not exist; the message suggests a close match if there is one
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// // Fails with "key 'opcua_machne1' not found; did you mean 'opcua_machine1'?"
+    /// let machine = config.get_or_suggest("opcua_machne1")?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_or_suggest(&self, key: &str) -> Result<&Value> {
+        if let Some(value) = self.get(key) {
+            return Ok(value);
+        }
+        let segments = split_key(key)?;
+        let mut parent = &self.data;
+        let mut depth = 0;
+        while let Some(next) = segments.get(depth).and_then(|part| child(parent, part)) {
+            parent = next;
+            depth += 1;
+        }
+        let Some(missing) = segments.get(depth).map(|part| part.name.as_ref()) else {
+            anyhow::bail!("key '{}' not found", key);
+        };
+        let suggestion = parent
+            .as_table()
+            .into_iter()
+            .flat_map(|table| table.keys())
+            .map(|sibling| (edit_distance(missing, sibling), sibling))
+            .filter(|(distance, _)| *distance <= 2 && *distance < missing.chars().count())
+            .min_by_key(|(distance, _)| *distance);
+        match suggestion {
+            Some((_, sibling)) => {
+                let mut suggested: Vec<String> = segments.iter().map(ToString::to_string).collect();
+                suggested[depth] = quote_key(sibling).into_owned();
+                anyhow::bail!(
+                    "key '{}' not found; did you mean '{}'?",
+                    key,
+                    suggested.join(".")
+                )
             }
+            None => anyhow::bail!("key '{}' not found", key),
+        }
+    }
 
-            current = current
-                .get_mut(part)
-                .ok_or_else(|| anyhow::anyhow!("Failed to navigate to '{}'", part))?;
-
-            if !current.is_table() {
-                anyhow::bail!("'{}' is not a table, cannot create nested keys", part);
+    /// Returns the depth of the deepest table or array nesting.
+    ///
+    /// The root table counts as depth 0, so a file of top-level scalars has
+    /// depth 0 and `[a.b]` has depth 2. The tree is walked without recursion,
+    /// so this is safe to call on pathologically deep configurations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_max_depth.toml");
+    /// # std::fs::write(&path, "name = \"plant\"\n[sources.machine1]\nnodes = [[1, 2]]\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.max_depth(), 4);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn max_depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut pending = vec![(&self.data, 0)];
+        while let Some((value, depth)) = pending.pop() {
+            match value {
+                Value::Table(table) => pending.extend(table.values().map(|v| (v, depth + 1))),
+                Value::Array(array) => pending.extend(array.iter().map(|v| (v, depth + 1))),
+                _ => continue,
             }
+            deepest = deepest.max(depth);
         }
-
-        let last_key = parts[parts.len() - 1];
-        current
-            .as_table_mut()
-            .ok_or_else(|| anyhow::anyhow!("Parent is not a table"))?
-            .insert(last_key.to_string(), value.into());
-
-        Ok(self)
+        deepest
     }
 
-    /// Returns the path to the configuration file.
+    /// Returns the number of top-level keys, without collecting them.
     ///
     /// # Examples
     ///
-    /// ```no_run
-    /// # use toml_config::TomlConfig;
-    /// let config = TomlConfig::load("config.toml")?;
-    /// println!("Config path: {:?}", config.get_path());
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_section_count.toml");
+    /// # std::fs::write(&path, "version = 2\n[server]\nport = 8080\n[sources.a]\n[sources.b]\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.section_count(), 3);
+    /// assert_eq!(config.child_count("sources"), Some(2));
+    /// # std::fs::remove_file(&path)?;
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn get_path(&self) -> &PathBuf {
-        &self.path
+    pub fn section_count(&self) -> usize {
+        self.data.as_table().map_or(0, |table| table.len())
     }
 
-    /// Returns a reference to the underlying TOML data.
+    /// Returns the number of entries in the table or array at the specified key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the table or array
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(usize)` if the key holds a table or an array, `None`
+    /// otherwise.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use toml_config::TomlConfig;
+    /// # use tomlreadwr::TomlConfig;
     /// let config = TomlConfig::load("config.toml")?;
-    /// println!("Raw data: {:?}", config.get_data());
+    /// println!("loaded {} sources", config.child_count("sources").unwrap_or(0));
     /// # Ok::<(), anyhow::Error>(())
     /// ```
-    pub fn get_data(&self) -> &Value {
-        &self.data
+    pub fn child_count(&self, key: &str) -> Option<usize> {
+        match self.get(key)? {
+            Value::Table(table) => Some(table.len()),
+            Value::Array(array) => Some(array.len()),
+            _ => None,
+        }
+    }
+
+    /// Returns the byte range of a value in the source text of the loaded file.
+    ///
+    /// For tables defined by a `[header]`, the range covers the header; for
+    /// arrays of tables, the first `[[header]]`. Spans refer to the text as it
+    /// was loaded, so values changed since then have none. The source is
+    /// parsed for spans once, on the first call, and reused until it is
+    /// reloaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Range)` if the key exists in the loaded text and is
+    /// unchanged, `None` otherwise, including for configurations that were not
+    /// parsed from text, such as those returned by [`extract`](Self::extract).
+    /// Configurations read with [`from_reader`](Self::from_reader) have spans
+    /// too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_span.toml");
+    /// # std::fs::write(&path, "[server]\nport = 80\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.span("server.port"), Some(16..18));
+    ///
+    /// let config = TomlConfig::from_reader("[server]\nport = 80\n".as_bytes())?;
+    /// assert_eq!(config.span("server.port"), Some(16..18));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn span(&self, key: &str) -> Option<Range<usize>> {
+        if resolve(&self.data, key)? != resolve(&self.baseline, key)? {
+            return None;
+        }
+        let source = self.source.as_deref()?;
+        let root = self
+            .spans
+            .get_or_init(|| {
+                let table = DeTable::parse(source).ok()?;
+                let span = table.span();
+                Some(SpanTree::new(&DeValue::Table(table.into_inner()), span))
+            })
+            .as_ref()?;
+        let mut current = root;
+        for part in split_key(key).ok()? {
+            current = match current.children.as_ref()? {
+                SpanChildren::Array(items) => items.get(part.index()?)?,
+                SpanChildren::Table(entries) => entries.get(part.name.as_ref())?,
+            };
+        }
+        Some(current.span.clone())
+    }
+
+    /// Returns the text of a value exactly as it was written in the loaded file.
+    ///
+    /// The literal keeps its original form, such as `0x1F4` for a hex integer
+    /// or a string with its original quotes. Tables and arrays of tables
+    /// defined by headers have no single literal and return `None`; inline
+    /// tables and arrays are returned whole. Like [`span`](Self::span), only
+    /// values unchanged since loading have a raw form.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(String)` if the key was written as a literal in the loaded
+    /// file and is unchanged, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_raw.toml");
+    /// # std::fs::write(&path, "[server]\nport = 0x1F4\nname = 'edge'\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.raw("server.port").as_deref(), Some("0x1F4"));
+    /// assert_eq!(config.raw("server.name").as_deref(), Some("'edge'"));
+    /// assert_eq!(config.raw("server"), None);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn raw(&self, key: &str) -> Option<String> {
+        let text = self.source.as_deref()?.get(self.span(key)?)?;
+        let reparsed: toml::Table = toml::from_str(&format!("value = {text}")).ok()?;
+        (reparsed.get("value") == self.get(key)).then(|| text.to_string())
+    }
+
+    fn location_of(&self, key: &str) -> Option<(usize, usize)> {
+        let start = self.span(key)?.start;
+        Some(line_column(self.source.as_deref()?, start))
+    }
+
+    /// Returns the kind of the value at the specified key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(ValueKind)` if the key exists, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::{TomlConfig, ValueKind};
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if config.kind("source.node_variance") != Some(ValueKind::Array) {
+    ///     eprintln!("node_variance must be an array");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn kind(&self, key: &str) -> Option<ValueKind> {
+        self.get(key).map(ValueKind::of)
+    }
+
+    /// Returns the TOML type name of the value at the specified key.
+    ///
+    /// The name is one of `"string"`, `"integer"`, `"float"`, `"boolean"`,
+    /// `"datetime"`, `"array"` or `"table"`, as given by
+    /// [`toml::Value::type_str`].
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(&'static str)` if the key exists, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_type_name.toml");
+    /// # std::fs::write(&path, "[server]\nport = \"8080\"\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.type_name("server.port"), Some("string"));
+    /// assert_eq!(config.type_name("server"), Some("table"));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn type_name(&self, key: &str) -> Option<&'static str> {
+        self.get(key).map(Value::type_str)
+    }
+
+    /// Distinguishes a missing array from an empty or non-empty one.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the array
+    ///
+    /// # Returns
+    ///
+    /// Returns [`ArrayState::Missing`] if the key does not exist or is not an
+    /// array, [`ArrayState::Empty`] for an empty array, and
+    /// [`ArrayState::NonEmpty`] with the length otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::{ArrayState, TomlConfig};
+    /// let config = TomlConfig::load("config.toml")?;
+    /// match config.array_state("source.node_variance") {
+    ///     ArrayState::Missing => println!("inherit defaults"),
+    ///     ArrayState::Empty => println!("explicitly none"),
+    ///     ArrayState::NonEmpty(n) => println!("{n} nodes"),
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn array_state(&self, key: &str) -> ArrayState {
+        match self.get(key).and_then(Value::as_array) {
+            None => ArrayState::Missing,
+            Some(array) if array.is_empty() => ArrayState::Empty,
+            Some(array) => ArrayState::NonEmpty(array.len()),
+        }
+    }
+
+    /// Returns whether all elements of an array share one [`ValueKind`].
+    ///
+    /// Only the top-level kind is compared, so an array of tables with
+    /// different fields still counts as homogeneous. An empty array is
+    /// homogeneous.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the array
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(bool)` if the key holds an array, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if config.is_homogeneous_array("source.node_variance") != Some(true) {
+    ///     eprintln!("node_variance must be an array of one type");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn is_homogeneous_array(&self, key: &str) -> Option<bool> {
+        let array = self.get(key)?.as_array()?;
+        let mut kinds = array.iter().map(ValueKind::of);
+        let first = kinds.next();
+        Some(kinds.all(|kind| Some(kind) == first))
+    }
+
+    /// Retrieves several values from the configuration at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - Dot-separated paths to look up
+    ///
+    /// # Returns
+    ///
+    /// Returns a map from each requested key to `Some(&Value)` if it exists,
+    /// or `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let values = config.get_many(&["server.host", "server.port"]);
+    /// let missing: Vec<_> = values.iter().filter(|(_, v)| v.is_none()).collect();
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_many(&self, keys: &[&str]) -> HashMap<String, Option<&Value>> {
+        keys.iter()
+            .map(|key| (key.to_string(), self.get(key)))
+            .collect()
+    }
+
+    /// Retrieves a value, following `${other.key}` references.
+    ///
+    /// If the value at `key` is a string of the exact form `"${path}"`, the
+    /// value at `path` is returned instead, following chains of references.
+    /// Any other value is returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Value)` if the key and every referenced key exist, `None`
+    /// if any of them is missing or the references form a cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// // [common]
+    /// // host = "10.0.0.5"
+    /// // [machine1]
+    /// // host = "${common.host}"
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let host = config.get_resolved("machine1.host");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_resolved(&self, key: &str) -> Option<Value> {
+        let mut visited = vec![key];
+        let mut value = self.get(key)?;
+        while let Some(reference) = value
+            .as_str()
+            .and_then(|s| s.strip_prefix("${"))
+            .and_then(|s| s.strip_suffix('}'))
+        {
+            if visited.contains(&reference) {
+                return None;
+            }
+            visited.push(reference);
+            value = self.get(reference)?;
+        }
+        Some(value.clone())
+    }
+
+    /// Retrieves a string value from the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(&str)` if the key exists and contains a string, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if let Some(host) = config.get_str("server.host") {
+    ///     println!("Host: {}", host);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_str(&self, key: impl AsRef<str>) -> Option<&str> {
+        self.get(key)?.as_str()
+    }
+
+    /// Retrieves a string value split on a separator.
+    ///
+    /// The parts borrow from the configuration, so no strings are allocated.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `sep` - Character to split on
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Vec<&str>)` if the key exists and contains a string,
+    /// `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_get_str_split.toml");
+    /// # std::fs::write(&path, "[server]\nlisten = \"4840/tcp\"\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.get_str_split("server.listen", '/'), Some(vec!["4840", "tcp"]));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_str_split(&self, key: &str, sep: char) -> Option<Vec<&str>> {
+        Some(self.get_str(key)?.split(sep).collect())
+    }
+
+    /// Retrieves a string value as a `Cow`, borrowing it from the configuration.
+    ///
+    /// Strings stored in the configuration are always returned as
+    /// `Cow::Borrowed`, so this never allocates today; the `Cow` leaves room for
+    /// computed values. Deserializing into an owned `String`, whether with
+    /// [`get_of_type`](Self::get_of_type) or as a struct field, always
+    /// allocates; use this or [`get_str`](Self::get_str) for plain leaves.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Cow<str>)` if the key exists and contains a string, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let host = config.get_cow_str("server.host").unwrap_or("localhost".into());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_cow_str(&self, key: impl AsRef<str>) -> Option<Cow<'_, str>> {
+        self.get_str(key).map(Cow::Borrowed)
+    }
+
+    /// Retrieves an integer value, also accepting a number written as a string.
+    ///
+    /// Surrounding whitespace in a string is ignored, so `namespace = " 4"`
+    /// reads as `4`. Floats and strings that are not whole numbers are not
+    /// converted.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(i64)` if the key holds an integer or a string that parses
+    /// as one, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_get_int_coerce.toml");
+    /// # std::fs::write(&path, "namespace = \"4\"\nport = 4840\nhost = \"edge\"\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.get_int_coerce("namespace"), Some(4));
+    /// assert_eq!(config.get_int_coerce("port"), Some(4840));
+    /// assert_eq!(config.get_int_coerce("host"), None);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_int_coerce(&self, key: &str) -> Option<i64> {
+        match self.get(key)? {
+            Value::Integer(n) => Some(*n),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Retrieves a boolean value, also accepting common string spellings.
+    ///
+    /// Besides TOML booleans, the strings `true`/`false`, `yes`/`no`,
+    /// `on`/`off` and `1`/`0` are accepted, ignoring case.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(bool)` if the key exists and holds a recognized value, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// // enabled = "yes"
+    /// let config = TomlConfig::load("config.toml")?;
+    /// assert_eq!(config.get_bool_lenient("enabled"), Some(true));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_bool_lenient(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            Value::Boolean(b) => Some(*b),
+            Value::String(s) => match s.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(true),
+                "false" | "no" | "off" | "0" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Retrieves an array of strings as slices borrowed from the configuration.
+    ///
+    /// Only the `Vec` is allocated; the strings themselves are not copied.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the array
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Vec<&str>)` if the key holds an array whose items are all
+    /// strings, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_get_str_array.toml");
+    /// # std::fs::write(&path, "node_variance = [\"ns=2;i=1\", \"ns=2;i=2\"]\nmixed = [\"a\", 1]\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.get_str_array("node_variance"), Some(vec!["ns=2;i=1", "ns=2;i=2"]));
+    /// assert_eq!(config.get_str_array("mixed"), None);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_str_array(&self, key: &str) -> Option<Vec<&str>> {
+        self.get(key)?
+            .as_array()?
+            .iter()
+            .map(Value::as_str)
+            .collect()
+    }
+
+    /// Retrieves an array of strings, also accepting a comma-separated string.
+    ///
+    /// A string value is split on commas and each item is trimmed; empty items
+    /// are dropped, so `"a, b,"` yields `["a", "b"]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Vec<String>)` if the key holds an array of strings or a
+    /// string, `None` otherwise, including for arrays with non-string items.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// // node_variance = "a,b,c" or node_variance = ["a", "b", "c"]
+    /// let config = TomlConfig::load("config.toml")?;
+    /// assert_eq!(
+    ///     config.get_array_lenient("node_variance"),
+    ///     Some(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_array_lenient(&self, key: &str) -> Option<Vec<String>> {
+        match self.get(key)? {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect(),
+            Value::String(s) => Some(
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Retrieves a datetime value from the configuration.
+    ///
+    /// TOML datetimes may be offset datetimes, local datetimes, local dates or
+    /// local times; inspect the `date`, `time` and `offset` fields of the
+    /// returned [`Datetime`](toml::value::Datetime) to tell them apart.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(&Datetime)` if the key exists and contains a datetime, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if let Some(started) = config.get_datetime("collection.started_at") {
+    ///     println!("Started at: {}", started);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_datetime(&self, key: &str) -> Option<&toml::value::Datetime> {
+        self.get(key)?.as_datetime()
+    }
+
+    /// Retrieves a string value parsed as a socket address.
+    ///
+    /// The string must hold an IP address and a port, such as
+    /// `"0.0.0.0:4840"` or `"[::1]:4840"`. Host names are not resolved.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(SocketAddr)` if the key exists and holds a valid socket
+    /// address, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// // listen = "0.0.0.0:4840"
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if let Some(addr) = config.get_socket_addr("server.listen") {
+    ///     println!("Listening on port {}", addr.port());
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_socket_addr(&self, key: &str) -> Option<SocketAddr> {
+        self.get_str(key)?.parse().ok()
+    }
+
+    /// Retrieves a string value parsed as an IPv4 or IPv6 address.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(IpAddr)` if the key exists and holds a valid IP address,
+    /// `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let invalid = config.filter_children("sources", |source| {
+    ///     source
+    ///         .get("machine_ip")
+    ///         .and_then(toml::Value::as_str)
+    ///         .is_none_or(|ip| ip.parse::<std::net::IpAddr>().is_err())
+    /// });
+    /// for (name, _) in invalid {
+    ///     eprintln!("{name}: invalid machine_ip");
+    /// }
+    /// let ip = config.get_ip_addr("sources.opcua_machine1.machine_ip");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_ip_addr(&self, key: &str) -> Option<IpAddr> {
+        self.get_str(key)?.parse().ok()
+    }
+
+    /// Deserializes a value at the specified key into a type `T`.
+    ///
+    /// The value is deserialized by reference, without cloning the subtree first.
+    /// Pass `""` as the key to deserialize the whole document.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the TOML value into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if the key exists and can be deserialized, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct ServerConfig {
+    ///     host: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let server: ServerConfig = config.get_of_type("server").unwrap();
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_of_type<T: for<'a> serde::Deserialize<'a>>(
+        &self,
+        key: impl AsRef<str>,
+    ) -> Option<T> {
+        let value = self.get(key)?;
+        T::deserialize(ValueRef::new(value)).ok()
+    }
+
+    /// Deserializes a value into a type `T`, rejecting keys `T` doesn't declare.
+    ///
+    /// Every struct in `T`, including nested ones, is treated as if it had
+    /// `#[serde(deny_unknown_fields)]`, so a table only matches a struct that
+    /// accounts for all of its keys. This tells a truly matching type apart
+    /// from a permissive partial match when trying several candidates.
+    /// Structs using `#[serde(flatten)]` accept any keys.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the TOML value into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if the key exists and can be deserialized without
+    /// unknown fields, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_get_of_type_strict.toml");
+    /// # std::fs::write(&path, "[source]\nhost = \"10.0.0.1\"\nport = 4840\n")?;
+    /// #[derive(serde::Deserialize)]
+    /// struct HostOnly {
+    ///     host: String,
+    /// }
+    ///
+    /// #[derive(serde::Deserialize)]
+    /// struct OpcuaConf {
+    ///     host: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// let config = TomlConfig::load(&path)?;
+    /// assert!(config.get_of_type::<HostOnly>("source").is_some());
+    /// assert!(config.get_of_type_strict::<HostOnly>("source").is_none());
+    /// assert!(config.get_of_type_strict::<OpcuaConf>("source").is_some());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_of_type_strict<T: for<'a> serde::Deserialize<'a>>(
+        &self,
+        key: impl AsRef<str>,
+    ) -> Option<T> {
+        let value = self.get(key)?;
+        T::deserialize(ValueRef::strict(value)).ok()
+    }
+
+    /// Deserializes a value at the specified key into a type `T`, reporting why it failed.
+    ///
+    /// Unlike [`get_of_type`](Self::get_of_type), a value that exists but does
+    /// not fit `T` is an error rather than `None`, and the error names the
+    /// offending field and the type that was expected.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the TOML value into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(T))` if the key exists and can be deserialized, or
+    /// `Ok(None)` if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DeserializeError`] if the value cannot be deserialized into
+    /// `T`. It carries the raw value that was found, and can be recovered
+    /// from the `anyhow::Error` with `downcast_ref`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct ServerConfig {
+    ///     host: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// match config.try_get_of_type::<ServerConfig>("server") {
+    ///     Ok(Some(server)) => println!("{}:{}", server.host, server.port),
+    ///     Ok(None) => println!("no server configured"),
+    ///     Err(e) => eprintln!("{e}"),
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// # use tomlreadwr::{DeserializeError, TomlConfig};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_try_get_of_type.toml");
+    /// # std::fs::write(&path, "[sources.opcua_machine1]\nport = \"4840\"\n")?;
+    /// #[derive(serde::Deserialize)]
+    /// struct OpcuaConf {
+    ///     port: u16,
+    /// }
+    ///
+    /// let config = TomlConfig::load(&path)?;
+    /// let err = config
+    ///     .try_get_of_type::<OpcuaConf>("sources.opcua_machine1")
+    ///     .err()
+    ///     .unwrap();
+    /// let err = err.downcast_ref::<DeserializeError>().unwrap();
+    /// assert_eq!(err.path(), "sources.opcua_machine1.port");
+    /// assert_eq!(err.value()["port"].as_str(), Some("4840"));
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Failed to deserialize 'sources.opcua_machine1.port' (line 2 column 8): \
+    ///      invalid type: string \"4840\", expected u16"
+    /// );
+    ///
+    /// // A value that is the wrong type as a whole reports the key itself.
+    /// let err = config.try_get_of_type::<u16>("sources.opcua_machine1.port");
+    /// let err = err.err().unwrap();
+    /// let err = err.downcast_ref::<DeserializeError>().unwrap();
+    /// assert_eq!(err.path(), "sources.opcua_machine1.port");
+    /// assert_eq!(err.line(), Some(2));
+    /// assert_eq!(err.column(), Some(8));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn try_get_of_type<T: for<'a> serde::Deserialize<'a>>(
+        &self,
+        key: impl AsRef<str>,
+    ) -> Result<Option<T>> {
+        let key = key.as_ref();
+        let Some(value) = self.get(key) else {
+            return Ok(None);
+        };
+        T::deserialize(ValueRef::new(value)).map(Some).map_err(|e| {
+            let path = match e.path() {
+                field if field.is_empty() => key.to_string(),
+                field => join_key(key, &field),
+            };
+            let location = self.location_of(&path);
+            DeserializeError::new(key, path, e.message(), value.clone())
+                .with_location(location)
+                .into()
+        })
+    }
+
+    /// Returns a typed handle to a section that keeps the deserialized value.
+    ///
+    /// See [`Section`] for reading and refreshing it.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the section into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the section
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key does not exist
+    /// - The value cannot be deserialized into `T`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct OpcuaConf {
+    ///     host: String,
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let machine = config.section::<OpcuaConf>("sources.opcua_machine1")?;
+    /// println!("{}", machine.get().host);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn section<T: for<'a> serde::Deserialize<'a>>(&self, key: &str) -> Result<Section<T>> {
+        Section::load(self, key)
+    }
+
+    /// Deserializes a value and checks it with a validation closure.
+    ///
+    /// The value is only returned if `validate` accepts it, so invariants
+    /// spanning several fields can be enforced at read time.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the value into
+    /// * `F` - Closure returning an error for invalid values
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `validate` - Closure run on the deserialized value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key does not exist
+    /// - The value cannot be deserialized into `T`, as a [`DeserializeError`]
+    /// - `validate` returns an error, with the key and, when known, its line
+    ///   in the file added as context
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_get_validated.toml");
+    /// # std::fs::write(&path, "[opcua]\ncollection_interval_seconds = 60\ncollection_duration = 30\n")?;
+    /// #[derive(serde::Deserialize)]
+    /// struct OpcuaConf {
+    ///     collection_interval_seconds: u64,
+    ///     collection_duration: u64,
+    /// }
+    ///
+    /// let config = TomlConfig::load(&path)?;
+    /// let result = config.get_validated("opcua", |conf: &OpcuaConf| {
+    ///     if conf.collection_interval_seconds > conf.collection_duration {
+    ///         anyhow::bail!("interval is longer than the collection duration");
+    ///     }
+    ///     Ok(())
+    /// });
+    /// let err = result.err().unwrap();
+    /// assert_eq!(err.to_string(), "Invalid value at 'opcua' (line 1)");
+    /// assert_eq!(
+    ///     err.root_cause().to_string(),
+    ///     "interval is longer than the collection duration"
+    /// );
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_validated<T, F>(&self, key: &str, validate: F) -> Result<T>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+        F: Fn(&T) -> Result<()>,
+    {
+        let value = self
+            .try_get_of_type::<T>(key)?
+            .ok_or_else(|| anyhow::anyhow!("Key '{key}' does not exist"))?;
+        validate(&value).map_err(|e| match self.location_of(key) {
+            Some((line, _)) => e.context(format!("Invalid value at '{key}' (line {line})")),
+            None => e.context(format!("Invalid value at '{key}'")),
+        })?;
+        Ok(value)
+    }
+
+    /// Deserializes a value at the specified key into a type `T`, falling back to `T::default()`.
+    ///
+    /// The fallback applies to the whole value: if the key is missing or any
+    /// field fails to deserialize, the default is returned. To tolerate
+    /// unknown values in a single enum field instead, give the enum a
+    /// catch-all variant marked `#[serde(untagged)]`:
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// #[serde(rename_all = "snake_case")]
+    /// enum SourceType {
+    ///     Opcua,
+    ///     Modbus,
+    ///     #[serde(untagged)]
+    ///     Other(String),
+    /// }
+    /// ```
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` and `Default`
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize, Default)]
+    /// struct Retry {
+    ///     attempts: u32,
+    ///     backoff_ms: u64,
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let retry: Retry = config.get_of_type_lenient("source.retry");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_of_type_lenient<T: for<'a> serde::Deserialize<'a> + Default>(
+        &self,
+        key: impl AsRef<str>,
+    ) -> T {
+        self.get_of_type(key).unwrap_or_default()
+    }
+
+    /// Deserializes a value only if another key holds an expected value.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the TOML value into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `cond_key` - Dot-separated path to the value to check
+    /// * `cond_value` - Value `cond_key` must hold
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if `cond_key` equals `cond_value` and `key` exists and
+    /// can be deserialized, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let user: Option<String> = config.get_if(
+    ///     "source.auth_user",
+    ///     "source.authtype",
+    ///     &toml::Value::from("basic"),
+    /// );
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_if<T: for<'a> serde::Deserialize<'a>>(
+        &self,
+        key: &str,
+        cond_key: &str,
+        cond_value: &Value,
+    ) -> Option<T> {
+        if self.get(cond_key)? != cond_value {
+            return None;
+        }
+        self.get_of_type(key)
+    }
+
+    /// Deserializes a single element of an array into a type `T`.
+    ///
+    /// Only the requested element is deserialized, not the whole array.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the element into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the array
+    /// * `index` - Position of the element in the array
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if the key holds an array with an element at `index`
+    /// that can be deserialized, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Machine {
+    ///     host: String,
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let third: Option<Machine> = config.get_array_item("sources.machines", 2);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_array_item<T: for<'a> serde::Deserialize<'a>>(
+        &self,
+        key: &str,
+        index: usize,
+    ) -> Option<T> {
+        let item = self.get(key)?.as_array()?.get(index)?;
+        T::deserialize(ValueRef::new(item)).ok()
+    }
+
+    /// Deserializes a value like [`get_of_type`](Self::get_of_type), caching the result.
+    ///
+    /// Results are cached per key and type. The cache is cleared by every
+    /// mutation, including overlay changes, so a cached value never goes stale.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the TOML value into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(Arc<T>)` if the key exists and can be deserialized, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct ServerConfig {
+    ///     host: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// for _ in 0..1000 {
+    ///     let server = config.get_of_type_cached::<ServerConfig>("server").unwrap();
+    ///     println!("{}:{}", server.host, server.port);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_of_type_cached<T>(&self, key: &str) -> Option<Arc<T>>
+    where
+        T: for<'a> serde::Deserialize<'a> + Send + Sync + 'static,
+    {
+        let cache_key = (key.to_string(), TypeId::of::<T>());
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cached) = cache.get(&cache_key) {
+            return cached.clone().downcast().ok();
+        }
+        let value = Arc::new(self.get_of_type::<T>(key)?);
+        cache.insert(cache_key, value.clone());
+        Some(value)
+    }
+
+    /// Parses the value at the specified key with a custom function.
+    ///
+    /// An escape hatch for values that need parsing beyond what `Deserialize`
+    /// offers.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `f` - Function converting the raw value
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the key does not exist, otherwise the result of `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let enabled = config.get_with("server.enabled", |value| match value.as_str() {
+    ///     Some("yes") => Ok(true),
+    ///     Some("no") => Ok(false),
+    ///     _ => anyhow::bail!("expected 'yes' or 'no'"),
+    /// });
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_with<T, F: FnOnce(&Value) -> Result<T>>(
+        &self,
+        key: &str,
+        f: F,
+    ) -> Option<Result<T>> {
+        self.get(key).map(f)
+    }
+
+    /// Deserializes a table merged on top of a table of shared defaults.
+    ///
+    /// The table at `override_key` is deep-merged over the table at `base_key`,
+    /// so nested tables are combined and override values win on conflict.
+    /// An override table that sets `inherit = false` opts out of the defaults
+    /// and is deserialized on its own; the `inherit` key stays in the value,
+    /// which `T` ignores unless it denies unknown fields.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the merged value into
+    ///
+    /// # Arguments
+    ///
+    /// * `base_key` - Dot-separated path to the defaults
+    /// * `override_key` - Dot-separated path to the overrides
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if both keys exist and the merged value can be
+    /// deserialized, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Machine {
+    ///     host: String,
+    ///     interval: u64,
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let machine: Machine = config
+    ///     .get_of_type_with_base("sources.defaults", "sources.machine1")
+    ///     .unwrap();
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_of_type_with_base<T: for<'a> serde::Deserialize<'a>>(
+        &self,
+        base_key: &str,
+        override_key: &str,
+    ) -> Option<T> {
+        let overrides = self.get(override_key)?;
+        if overrides.get("inherit").and_then(Value::as_bool) == Some(false) {
+            return T::deserialize(ValueRef::new(overrides)).ok();
+        }
+        // Merging needs an owned copy of the base anyway, so the merged value
+        // is deserialized by value rather than through `ValueRef`.
+        let mut merged = self.get(base_key)?.clone();
+        deep_merge(&mut merged, overrides);
+        T::deserialize(merged).ok()
+    }
+
+    /// Deserializes a table merged with its sub-table for one environment.
+    ///
+    /// The `[key.<env>]` sub-table is deep-merged over the table at `key`, so
+    /// per-environment values win. The base table is used alone if it has no
+    /// sub-table for `env`. Sub-tables for other environments stay in the
+    /// merged value, which `T` ignores unless it denies unknown fields.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the merged value into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the base table
+    /// * `env` - Name of the environment sub-table, e.g. `"prod"`
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(T)` if the key holds a table and the merged value can be
+    /// deserialized, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Machine {
+    ///     host: String,
+    ///     interval: u64,
+    /// }
+    ///
+    /// let env = std::env::var("APP_ENV").unwrap_or_else(|_| "dev".to_string());
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let machine: Machine = config
+    ///     .get_of_type_for_env("sources.opcua_machine1", &env)
+    ///     .unwrap();
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_of_type_for_env<T: for<'a> serde::Deserialize<'a>>(
+        &self,
+        key: &str,
+        env: &str,
+    ) -> Option<T> {
+        let mut merged = Value::Table(self.get(key)?.as_table()?.clone());
+        if let Some(overrides) = merged.as_table_mut()?.remove(env) {
+            deep_merge(&mut merged, &overrides);
+        }
+        // As in `get_of_type_with_base`, the merge already owns its copy.
+        T::deserialize(merged).ok()
+    }
+
+    /// Returns the entries of a table whose values match a predicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the table
+    /// * `pred` - Predicate called with each child value
+    ///
+    /// # Returns
+    ///
+    /// Returns the matching `(name, value)` pairs, or an empty `Vec` if the key
+    /// does not exist or is not a table.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let enabled = config.filter_children("sources", |source| {
+    ///     source.get("enabled").and_then(|e| e.as_bool()) == Some(true)
+    /// });
+    /// for (name, _) in enabled {
+    ///     println!("{name} is enabled");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn filter_children<F: Fn(&Value) -> bool>(
+        &self,
+        key: &str,
+        pred: F,
+    ) -> Vec<(String, &Value)> {
+        let Some(table) = self.get(key).and_then(Value::as_table) else {
+            return Vec::new();
+        };
+        table
+            .iter()
+            .filter(|(_, value)| pred(value))
+            .map(|(name, value)| (name.clone(), value))
+            .collect()
+    }
+
+    /// Counts the entries of a table grouped by a key function.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - Dot-separated path to the table
+    /// * `f` - Function mapping each child value to its group
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of children in each group, or an empty map if the
+    /// key does not exist or is not a table.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let counts = config.group_count_by("sources", |source| {
+    ///     source.get("enabled").and_then(toml::Value::as_bool).unwrap_or(false)
+    /// });
+    /// println!("{} enabled, {} disabled", counts.get(&true).unwrap_or(&0), counts.get(&false).unwrap_or(&0));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn group_count_by<K: Eq + Hash, F: Fn(&Value) -> K>(
+        &self,
+        parent: &str,
+        f: F,
+    ) -> HashMap<K, usize> {
+        let mut counts = HashMap::new();
+        if let Some(table) = self.get(parent).and_then(Value::as_table) {
+            for value in table.values() {
+                *counts.entry(f(value)).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Returns every value stored under a given key name, at any depth.
+    ///
+    /// The tree is searched depth-first, including tables inside arrays, and
+    /// each match is returned with its full dotted path. Matches are not
+    /// searched further, so a `host` table inside a `host` table is not
+    /// reported twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `suffix` - Final path segment to match, e.g. `"host"`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// for (path, host) in config.get_all_by_suffix("host") {
+    ///     println!("{path} = {host}");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_all_by_suffix(&self, suffix: &str) -> Vec<(String, &Value)> {
+        let mut matches = Vec::new();
+        collect_by_suffix("", &self.data, suffix, &mut matches);
+        matches
+    }
+
+    /// Finds values of a field that are shared by more than one entry of a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - Dot-separated path to the table
+    /// * `field` - Name of the field to compare in each child
+    ///
+    /// # Returns
+    ///
+    /// Returns each duplicated value with the names of the children holding
+    /// it, in key order. Children without the field are ignored. Returns an
+    /// empty `Vec` if the key does not exist or is not a table.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// for (ip, machines) in config.duplicate_values("sources", "machine_ip") {
+    ///     eprintln!("{ip} is used by {}", machines.join(", "));
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn duplicate_values(&self, parent: &str, field: &str) -> Vec<(Value, Vec<String>)> {
+        let mut groups: Vec<(Value, Vec<String>)> = Vec::new();
+        if let Some(table) = self.get(parent).and_then(Value::as_table) {
+            for (name, child) in table {
+                let Some(value) = child.get(field) else {
+                    continue;
+                };
+                match groups.iter_mut().find(|(seen, _)| seen == value) {
+                    Some((_, names)) => names.push(name.clone()),
+                    None => groups.push((value.clone(), vec![name.clone()])),
+                }
+            }
+        }
+        groups.retain(|(_, names)| names.len() > 1);
+        groups
+    }
+
+    /// Extracts the table at the specified key into a standalone configuration.
+    ///
+    /// The subtree is cloned and becomes the root of the new configuration.
+    /// The returned configuration has an empty path, so it must be written
+    /// with [`save_as`](Self::save_as).
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the table to extract
+    ///
+    /// # Returns
+    ///
+    /// Returns `Some(TomlConfig)` if the key exists and contains a table, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if let Some(database) = config.extract("database") {
+    ///     database.save_as("database.toml")?;
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn extract(&self, key: &str) -> Option<TomlConfig> {
+        let value = self.get(key)?;
+        if !value.is_table() {
+            return None;
+        }
+        Some(TomlConfig::from_parts(value.clone(), PathBuf::new()))
+    }
+
+    /// Builds a configuration holding only the values that differ from a template.
+    ///
+    /// Tables are compared key by key, so only the differing leaves are kept,
+    /// together with the tables leading to them. Arrays are compared as a
+    /// whole. Keys that exist only in `defaults` are not represented. Like
+    /// [`extract`](Self::extract), the result has an empty path and must be
+    /// written with [`save_as`](Self::save_as).
+    ///
+    /// # Arguments
+    ///
+    /// * `defaults` - Template configuration to compare against
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let dir = std::env::temp_dir();
+    /// # std::fs::write(dir.join("tomlreadwr_minimize_defaults.toml"), "[source]\nport = 4840\ninterval = 10\n")?;
+    /// # std::fs::write(dir.join("tomlreadwr_minimize_machine.toml"), "[source]\nport = 4840\ninterval = 30\n")?;
+    /// let defaults = TomlConfig::load(dir.join("tomlreadwr_minimize_defaults.toml"))?;
+    /// let machine = TomlConfig::load(dir.join("tomlreadwr_minimize_machine.toml"))?;
+    /// let overrides = machine.minimize_against(&defaults);
+    /// assert_eq!(overrides.preview_save()?, "[source]\ninterval = 30\n");
+    /// # std::fs::remove_file(dir.join("tomlreadwr_minimize_defaults.toml"))?;
+    /// # std::fs::remove_file(dir.join("tomlreadwr_minimize_machine.toml"))?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn minimize_against(&self, defaults: &TomlConfig) -> TomlConfig {
+        let data = minimize(&self.data, &defaults.data)
+            .unwrap_or_else(|| Value::Table(toml::map::Map::new()));
+        TomlConfig::from_parts(data, PathBuf::new())
+    }
+
+    /// Sets a value in the configuration at the specified key.
+    ///
+    /// The parent path must exist. Use [`create`](Self::create) to create nested paths.
+    /// When the parent is an array, the last segment must index an existing
+    /// element, which is replaced. Strings containing line breaks are saved
+    /// as `"""` multiline strings.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `value` - Value to set (must be convertible to `toml::Value`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key has an empty segment (e.g. `""`, `"a..b"` or `".a"`)
+    /// - Any part of the parent path does not exist
+    /// - Any part of the parent path is not a table
+    /// - The parent is an array and the last segment is not an index within it
+    /// - The key, or a key above or below it, is locked with [`lock_key`](Self::lock_key)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 8080)?;
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// Array elements are addressed by index:
+    ///
+    /// ```
+    /// # use tomlreadwr::{KeyPath, TomlConfig};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_set_array_element.toml");
+    /// # std::fs::write(&path, "nodes = [\"ns=2;s=Temp\", \"ns=2;s=Speed\"]\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.set(KeyPath::new().key("nodes").index(0), "ns=2;s=Pressure")?;
+    /// assert_eq!(config.get_str("nodes.0"), Some("ns=2;s=Pressure"));
+    /// assert!(config.set("nodes.2", "ns=2;s=Level").is_err());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// Empty segments are rejected by `set`, [`create`](Self::create) and
+    /// [`delete`](Self::delete) alike:
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_set_empty_segment.toml");
+    /// # std::fs::write(&path, "[a]\nb = 1\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// for key in ["", ".", "a..b", ".a", "a."] {
+    ///     for err in [
+    ///         config.set(key, 2).err(),
+    ///         config.create(key, 2).err(),
+    ///         config.delete(key).err(),
+    ///     ] {
+    ///         assert!(err.unwrap().to_string().starts_with("invalid key: empty segment"));
+    ///     }
+    /// }
+    /// assert_eq!(config.get("a.b").and_then(|v| v.as_integer()), Some(1));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set<T: Into<Value>>(&mut self, key: impl AsRef<str>, value: T) -> Result<&mut Self> {
+        let key = key.as_ref();
+        self.check_writable(key)?;
+        insert_existing(self.data_mut(), key, value.into())?;
+        self.notify(key, ChangeKind::Set);
+        Ok(self)
+    }
+
+    /// Exchanges the values at two keys.
+    ///
+    /// Both keys are checked before anything changes, so the swap either
+    /// happens completely or not at all. Keys may address array elements,
+    /// e.g. `"servers.0"`. On success, `on_change` hooks see a
+    /// [`ChangeKind::Set`] for each key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_a` - Dot-separated path to the first value
+    /// * `key_b` - Dot-separated path to the second value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Either key does not exist
+    /// - One key is contained in the other
+    /// - Either key is locked or rejected by the write policy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_swap.toml");
+    /// # std::fs::write(&path, "[hosts]\nprimary = \"10.0.0.1\"\nbackup = \"10.0.0.2\"\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.swap("hosts.primary", "hosts.backup")?;
+    /// assert_eq!(config.get_str("hosts.primary"), Some("10.0.0.2"));
+    /// assert_eq!(config.get_str("hosts.backup"), Some("10.0.0.1"));
+    ///
+    /// assert!(config.swap("hosts.primary", "hosts.missing").is_err());
+    /// assert_eq!(config.get_str("hosts.primary"), Some("10.0.0.2"));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn swap(&mut self, key_a: &str, key_b: &str) -> Result<&mut Self> {
+        let (names_a, names_b) = (key_names(key_a), key_names(key_b));
+        let shared = names_a.len().min(names_b.len());
+        if names_a[..shared] == names_b[..shared] {
+            anyhow::bail!("Cannot swap '{key_a}' with '{key_b}': one contains the other");
+        }
+        self.check_writable(key_a)?;
+        self.check_writable(key_b)?;
+        if resolve(&self.data, key_a).is_none() {
+            anyhow::bail!("Key '{key_a}' does not exist");
+        }
+        let value_b = resolve(&self.data, key_b)
+            .ok_or_else(|| anyhow::anyhow!("Key '{key_b}' does not exist"))?
+            .clone();
+        // Both paths exist and neither contains the other, so resolving them
+        // again cannot fail and the swap is never left half done.
+        let data = self.data_mut();
+        let slot_a = resolve_mut(data, key_a).expect("key_a was resolved above");
+        let value_a = std::mem::replace(slot_a, value_b);
+        *resolve_mut(data, key_b).expect("key_b was resolved above") = value_a;
+        self.notify(key_a, ChangeKind::Set);
+        self.notify(key_b, ChangeKind::Set);
+        Ok(self)
+    }
+
+    /// Sets an integer value after checking it lies within `[min, max]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `value` - Integer to set
+    /// * `min` - Smallest allowed value (inclusive)
+    /// * `max` - Largest allowed value (inclusive)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `value` is outside `[min, max]`
+    /// - Any of the conditions of [`set`](Self::set) apply
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set_int_ranged("source.collection_interval_seconds", 60, 1, 86400)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_int_ranged(
+        &mut self,
+        key: &str,
+        value: i64,
+        min: i64,
+        max: i64,
+    ) -> Result<&mut Self> {
+        if !(min..=max).contains(&value) {
+            anyhow::bail!("Value {value} for '{key}' is outside the range [{min}, {max}]");
+        }
+        self.set(key, value)
+    }
+
+    /// Sets a value only if the current value equals `expected`.
+    ///
+    /// Overlays are not consulted; the comparison is made against the loaded data.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `expected` - Value the key must currently hold
+    /// * `new` - Value to set (must be convertible to `toml::Value`)
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the value was swapped, `false` if the key is missing
+    /// or holds a different value.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`set`](Self::set).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// if !config.compare_and_set("server.port", &8080.into(), 9090)? {
+    ///     println!("server.port was changed by someone else");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn compare_and_set<T: Into<Value> + PartialEq>(
+        &mut self,
+        key: &str,
+        expected: &Value,
+        new: T,
+    ) -> Result<bool> {
+        if resolve(&self.data, key) != Some(expected) {
+            return Ok(false);
+        }
+        self.set(key, new)?;
+        Ok(true)
+    }
+
+    /// Deletes a value from the configuration at the specified key.
+    ///
+    /// A numeric last segment removes an array element, e.g. `"servers.0"`;
+    /// later elements move down by one index.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value to delete
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key has an empty segment (e.g. `""`, `"a..b"` or `".a"`)
+    /// - Any part of the parent path does not exist
+    /// - Any part of the parent path is not a table or an array
+    /// - The key, or a key above or below it, is locked with [`lock_key`](Self::lock_key)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.delete("server.debug_mode")?;
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn delete(&mut self, key: impl AsRef<str>) -> Result<&mut Self> {
+        let key = key.as_ref();
+        self.check_writable(key)?;
+        if remove(self.data_mut(), key)?.is_some() {
+            self.notify(key, ChangeKind::Delete);
+        }
+        Ok(self)
+    }
+
+    /// Removes a value and returns it deserialized into type `T`.
+    ///
+    /// The value is only removed if it deserializes, so a type mismatch leaves
+    /// the configuration unchanged. Array elements can be taken too, as with
+    /// [`delete`](Self::delete).
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T` - Type implementing `Deserialize` to convert the value into
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value to remove
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(T))` with the removed value, or `Ok(None)` if the key
+    /// does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, leaving the value in place, if:
+    /// - The value cannot be deserialized into `T`
+    /// - The value cannot be deleted, e.g. because it is locked
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct OpcuaConf {
+    ///     host: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// if let Some(archived) = config.take_of_type::<OpcuaConf>("opcua_machine1")? {
+    ///     println!("decommissioned {}:{}", archived.host, archived.port);
+    /// }
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_take_of_type.toml");
+    /// # std::fs::write(&path, "ports = [4840, 4841]\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// assert_eq!(config.take_of_type::<u16>("ports.0")?, Some(4840));
+    /// assert_eq!(config.get_of_type::<Vec<u16>>("ports"), Some(vec![4841]));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn take_of_type<T: for<'a> serde::Deserialize<'a>>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>> {
+        let Some(value) = resolve(&self.data, key) else {
+            return Ok(None);
+        };
+        let taken = T::deserialize(ValueRef::new(value))?;
+        self.delete(key)?;
+        Ok(Some(taken))
+    }
+
+    /// Moves the value at one key to another key.
+    ///
+    /// Missing parent tables of the destination are created as needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - Dot-separated path of the value to move
+    /// * `to` - Dot-separated destination path
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `from` does not exist
+    /// - `to` already exists
+    /// - A non-table value exists in the destination path where a table is needed
+    /// - `from` or `to`, or a key above or below them, is locked with [`lock_key`](Self::lock_key)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.rename_key("source.interval", "source.collection_interval_seconds")?;
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn rename_key(&mut self, from: &str, to: &str) -> Result<&mut Self> {
+        self.check_writable(from)?;
+        self.check_writable(to)?;
+        if resolve(&self.data, to).is_some() {
+            anyhow::bail!("Key '{to}' already exists");
+        }
+        let value = remove(self.data_mut(), from)?
+            .ok_or_else(|| anyhow::anyhow!("Key '{from}' does not exist"))?;
+        if let Err(e) = insert_creating(self.data_mut(), to, value.clone()) {
+            insert_creating(self.data_mut(), from, value)?;
+            return Err(e);
+        }
+        self.notify(from, ChangeKind::Delete);
+        self.notify(to, ChangeKind::Create);
+        Ok(self)
+    }
+
+    /// Applies several renames in order.
+    ///
+    /// Each `(from, to)` pair is handled like [`rename_key`](Self::rename_key).
+    /// Renames applied before a failure are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `mapping` - `(from, to)` pairs of dot-separated paths
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the failed rename and the renames that
+    /// succeeded before it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.rename_many(&[
+    ///     ("source.interval", "source.collection_interval_seconds"),
+    ///     ("source.ip", "source.machine_ip"),
+    /// ])?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn rename_many(&mut self, mapping: &[(&str, &str)]) -> Result<&mut Self> {
+        for (index, (from, to)) in mapping.iter().enumerate() {
+            if let Err(e) = self.rename_key(from, to) {
+                let done: Vec<String> = mapping[..index]
+                    .iter()
+                    .map(|(from, to)| format!("'{from}' -> '{to}'"))
+                    .collect();
+                anyhow::bail!(
+                    "Rename '{from}' -> '{to}' failed after renaming [{}]: {e}",
+                    done.join(", ")
+                );
+            }
+        }
+        Ok(self)
+    }
+
+    /// Renames a field in every child table of a table.
+    ///
+    /// Each rename is done with [`rename_key`](Self::rename_key). Children that
+    /// are not tables or lack `from_field` are skipped. If a rename fails, the
+    /// renames already applied are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - Dot-separated path to the table whose children are renamed
+    /// * `from_field` - Current field name
+    /// * `to_field` - New field name
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of children that were changed; `0` if `parent` does
+    /// not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `parent` exists but is not a table
+    /// - A child already has `to_field`, or `rename_key` fails otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// let migrated =
+    ///     config.rename_field_in_children("sources", "interval", "collection_interval_seconds")?;
+    /// println!("migrated {migrated} machines");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn rename_field_in_children(
+        &mut self,
+        parent: &str,
+        from_field: &str,
+        to_field: &str,
+    ) -> Result<usize> {
+        let Some(value) = self.get(parent) else {
+            return Ok(0);
+        };
+        let table = value
+            .as_table()
+            .ok_or_else(|| anyhow::anyhow!("'{parent}' is not a table"))?;
+        let children: Vec<String> = table
+            .iter()
+            .filter(|(_, child)| child.get(from_field).is_some())
+            .map(|(name, _)| join_key(parent, &quote_key(name)))
+            .collect();
+        for child in &children {
+            self.rename_key(
+                &join_key(child, &quote_key(from_field)),
+                &join_key(child, &quote_key(to_field)),
+            )?;
+        }
+        Ok(children.len())
+    }
+
+    /// Rewrites every table key, recursively, in the given naming style.
+    ///
+    /// Keys in tables inside arrays are rewritten too. Values are left
+    /// untouched. The rewrite is all or nothing: on error the configuration
+    /// is unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - The naming style to convert keys to
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if two keys of the same table convert to the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::{KeyCase, TomlConfig};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_normalize_key_case.toml");
+    /// # std::fs::write(&path, "[source]\ncollection-interval-seconds = 60\nmachineIp = \"10.0.0.1\"\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.normalize_key_case(KeyCase::SnakeCase)?;
+    /// assert!(config.get("source.collection_interval_seconds").is_some());
+    /// assert!(config.get("source.machine_ip").is_some());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn normalize_key_case(&mut self, style: KeyCase) -> Result<&mut Self> {
+        let mut data = self.data.clone();
+        normalize_keys(&mut data, style, "")?;
+        *self.data_mut() = data;
+        Ok(self)
+    }
+
+    /// Merges another configuration into this one, resolving conflicts with a closure.
+    ///
+    /// Tables are merged recursively and keys missing from `self` are copied
+    /// from `other`. When both sides hold different values at the same path,
+    /// `resolver` is called with the dotted path, the current value and the
+    /// value from `other`, and its result is stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Configuration to merge in
+    /// * `resolver` - Closure picking the value to keep on a conflict
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use toml::Value;
+    ///
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// let fleet = TomlConfig::load("fleet.toml")?;
+    /// config.merge_with(&fleet, |path, ours, theirs| {
+    ///     match (path.ends_with("collection_interval_seconds"), ours, theirs) {
+    ///         (true, Value::Integer(a), Value::Integer(b)) => Value::Integer(*a.max(b)),
+    ///         _ => theirs.clone(),
+    ///     }
+    /// });
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn merge_with<F: Fn(&str, &Value, &Value) -> Value>(
+        &mut self,
+        other: &TomlConfig,
+        resolver: F,
+    ) -> &mut Self {
+        merge_resolving(self.data_mut(), &other.data, "", &resolver);
+        self
+    }
+
+    /// Deep-merges another configuration into this one and reports what it overrode.
+    ///
+    /// Tables are merged recursively, keys missing from `self` are copied from
+    /// `other`, and on a conflict the value from `other` wins, as with
+    /// [`merge_with`](Self::merge_with) and a resolver that always picks it.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Configuration to merge in
+    ///
+    /// # Returns
+    ///
+    /// Returns `(path, old value, new value)` for every path where `other`
+    /// replaced a differing value. Newly added keys are not listed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// use toml::Value;
+    ///
+    /// # let dir = std::env::temp_dir();
+    /// # std::fs::write(dir.join("tomlreadwr_merge_reporting_a.toml"), "[server]\nport = 8080\nhost = \"0.0.0.0\"\n")?;
+    /// # std::fs::write(dir.join("tomlreadwr_merge_reporting_b.toml"), "[server]\nport = 9090\nhost = \"0.0.0.0\"\ndebug = true\n")?;
+    /// let mut config = TomlConfig::load(dir.join("tomlreadwr_merge_reporting_a.toml"))?;
+    /// let overrides = TomlConfig::load(dir.join("tomlreadwr_merge_reporting_b.toml"))?;
+    /// let conflicts = config.merge_reporting(&overrides);
+    /// assert_eq!(
+    ///     conflicts,
+    ///     vec![("server.port".to_string(), Value::Integer(8080), Value::Integer(9090))]
+    /// );
+    /// assert_eq!(config.get("server.debug"), Some(&Value::Boolean(true)));
+    /// # std::fs::remove_file(dir.join("tomlreadwr_merge_reporting_a.toml"))?;
+    /// # std::fs::remove_file(dir.join("tomlreadwr_merge_reporting_b.toml"))?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn merge_reporting(&mut self, other: &TomlConfig) -> Vec<(String, Value, Value)> {
+        let conflicts = std::cell::RefCell::new(Vec::new());
+        merge_resolving(self.data_mut(), &other.data, "", &|path, ours, theirs| {
+            conflicts
+                .borrow_mut()
+                .push((path.to_string(), ours.clone(), theirs.clone()));
+            theirs.clone()
+        });
+        conflicts.into_inner()
+    }
+
+    /// Copies keys from a template that are missing from this configuration.
+    ///
+    /// Tables are filled recursively. Existing values are never overwritten,
+    /// even where the template holds a value of a different type.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Configuration holding the default for every key
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let template = TomlConfig::load("template.conf")?;
+    /// let mut config = TomlConfig::load("sources.conf")?;
+    /// config.fill_defaults_from(&template);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn fill_defaults_from(&mut self, template: &TomlConfig) -> &mut Self {
+        fill_missing(self.data_mut(), &template.data);
+        self
+    }
+
+    /// Saves the current configuration back to the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - The file cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 8080)?;
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// Non-finite floats are rejected instead of being written:
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_save_non_finite.toml");
+    /// # std::fs::write(&path, "[server]\nratio = 0.5\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.set("server.ratio", f64::NAN)?;
+    /// let err = config.save().unwrap_err();
+    /// assert_eq!(err.to_string(), "Cannot save non-finite float at 'server.ratio'");
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save(&self) -> Result<()> {
+        let mut content = Vec::new();
+        self.write_to(&mut content)?;
+        self.write_file(&content)
+    }
+
+    /// Rewrites the file with the current data, even if nothing changed.
+    ///
+    /// This writes exactly what [`save`](Self::save) would and exists to make
+    /// the intent clear at call sites: the write updates the file's
+    /// modification time, which prompts processes watching the file to reload.
+    /// The new modification time is recorded, so the configuration does not
+    /// become [stale](Self::is_stale) through its own touch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - The file cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// assert!(config.changed_keys().is_empty());
+    /// config.touch()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_touch.toml");
+    /// # std::fs::write(&path, "[server]\nport = 8080\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.touch()?;
+    /// assert!(!config.is_stale()?);
+    /// config.save_checked()?;
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn touch(&self) -> Result<()> {
+        self.save()
+    }
+
+    /// Returns the keys that were added, changed or removed since loading.
+    ///
+    /// Tables are compared entry by entry, so the returned keys are the
+    /// deepest paths that differ. Arrays are compared as a whole.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 9090)?;
+    /// assert_eq!(config.changed_keys(), vec!["server.port".to_string()]);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn changed_keys(&self) -> Vec<String> {
+        let mut changes = Vec::new();
+        collect_changes("", Some(&self.baseline), Some(&self.data), &mut changes);
+        changes.into_iter().map(|(key, _)| key).collect()
+    }
+
+    /// Serializes the changes made since loading as a standalone TOML patch.
+    ///
+    /// Added and changed values are written in nested form at their full
+    /// path. Removed keys are listed, as dotted paths, in a top-level
+    /// `__removed__` array of strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the patch cannot be serialized to TOML.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 9090)?.delete("server.debug_mode")?;
+    /// // __removed__ = ["server.debug_mode"]
+    /// //
+    /// // [server]
+    /// // port = 9090
+    /// std::fs::write("config.patch.toml", config.to_patch()?)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn to_patch(&self) -> Result<String> {
+        let mut changes = Vec::new();
+        collect_changes("", Some(&self.baseline), Some(&self.data), &mut changes);
+
+        let mut patch = Value::Table(toml::map::Map::new());
+        let mut removed = Vec::new();
+        for (key, value) in changes {
+            match value {
+                Some(value) => insert_creating(&mut patch, &key, value.clone())?,
+                None => removed.push(Value::String(key)),
+            }
+        }
+        if !removed.is_empty() {
+            insert_creating(&mut patch, "__removed__", Value::Array(removed))?;
+        }
+        Ok(toml::to_string(&patch)?)
+    }
+
+    /// Compares this configuration against a baseline file on disk.
+    ///
+    /// The file is only read, never modified. Tables are compared entry by
+    /// entry, so the reported keys are the deepest paths that differ. Arrays
+    /// are compared as a whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the baseline TOML file
+    ///
+    /// # Returns
+    ///
+    /// Returns one [`ConfigChange`] per differing key: `Added` for keys only in
+    /// `self`, `Removed` for keys only in the file, and `Changed` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or contains invalid TOML.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::{ConfigChange, TomlConfig};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_diff_against_file.toml");
+    /// # std::fs::write(&path, "[server]\nport = 8080\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.set("server.port", 9090)?;
+    /// let changes = config.diff_against_file(&path)?;
+    /// assert_eq!(
+    ///     changes,
+    ///     vec![ConfigChange::Changed {
+    ///         key: "server.port".to_string(),
+    ///         old: toml::Value::Integer(8080),
+    ///         new: toml::Value::Integer(9090),
+    ///     }]
+    /// );
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn diff_against_file(&self, path: impl AsRef<Path>) -> Result<Vec<ConfigChange>> {
+        let baseline = TomlConfig::load(path)?;
+        let mut changes = Vec::new();
+        collect_config_changes("", Some(&baseline.data), Some(&self.data), &mut changes);
+        Ok(changes)
+    }
+
+    /// Lists the paths whose value kind differs between two configurations.
+    ///
+    /// Only keys present in both are compared, and value changes of the same
+    /// kind are ignored. Tables are compared key by key and arrays element by
+    /// element; below a path whose kind differs nothing more is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Configuration to compare against
+    ///
+    /// # Returns
+    ///
+    /// Returns `(path, kind in self, kind in other)` for every mismatch.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let reference = TomlConfig::load("reference.toml")?;
+    /// let actual = TomlConfig::load("config.toml")?;
+    /// for (path, expected, found) in reference.type_diff(&actual) {
+    ///     eprintln!("{path}: expected {expected:?}, found {found:?}");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn type_diff(&self, other: &TomlConfig) -> Vec<(String, ValueKind, ValueKind)> {
+        let mut diffs = Vec::new();
+        collect_type_diffs("", &self.data, &other.data, &mut diffs);
+        diffs
+    }
+
+    /// Sets a comment block written at the top of the file on save.
+    ///
+    /// Every line of `header` is prefixed with `#`, and a blank line separates
+    /// the header from the configuration itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Comment text, possibly spanning multiple lines
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config
+    ///     .set_file_header("DO NOT EDIT - generated by provisioning")
+    ///     .save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_file_header(&mut self, header: &str) -> &mut Self {
+        self.header = Some(header.to_string());
+        self
+    }
+
+    /// Recursively removes tables that are empty.
+    ///
+    /// Tables left empty once their empty children are removed are removed
+    /// as well. Tables inside arrays are kept so element indices don't shift.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_prune_empty.toml");
+    /// # std::fs::write(&path, "[sources.old_machine.auth]\nuser = \"admin\"\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.delete("sources.old_machine.auth.user")?;
+    /// config.prune_empty();
+    /// assert!(config.get("sources").is_none());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn prune_empty(&mut self) -> &mut Self {
+        prune_empty_tables(self.data_mut());
+        self
+    }
+
+    /// Enables or disables pruning empty tables whenever the configuration is saved.
+    ///
+    /// Pruning on save only affects the written file; the in-memory data is
+    /// left untouched. See [`prune_empty`](Self::prune_empty).
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to prune empty tables on save
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set_prune_on_save(true).delete("server.debug_mode")?.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_prune_on_save(&mut self, enabled: bool) -> &mut Self {
+        self.prune_on_save = enabled;
+        self
+    }
+
+    /// Sets how float values are written when the configuration is saved.
+    ///
+    /// The default, [`FloatFormat::Shortest`], writes the shortest form that
+    /// reads back as the same value. [`FloatFormat::Fixed`] rounds floats to a
+    /// number of decimal places first, so values that differ only by float
+    /// noise, such as `0.30000000000000004` and `0.3`, are written the same.
+    /// Only the written file is affected; the in-memory data is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The float format to use on save
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::{FloatFormat, TomlConfig};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_float_format.toml");
+    /// # std::fs::write(&path, "ratio = 0.5\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.set("ratio", 0.1 + 0.2)?;
+    /// config.set_float_format(FloatFormat::Fixed(3));
+    /// assert_eq!(config.preview_save()?, "ratio = 0.3\n");
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_float_format(&mut self, format: FloatFormat) -> &mut Self {
+        self.float_format = format;
+        self
+    }
+
+    fn render(&self) -> Result<String> {
+        self.render_value(&self.saved_data())
+    }
+
+    fn saved_data(&self) -> Cow<'_, Value> {
+        if !self.prune_on_save && self.float_format == FloatFormat::Shortest {
+            return Cow::Borrowed(&self.data);
+        }
+        let mut data = self.data.clone();
+        if self.prune_on_save {
+            prune_empty_tables(&mut data);
+        }
+        format_floats(&mut data, self.float_format);
+        Cow::Owned(data)
+    }
+
+    fn render_value(&self, data: &Value) -> Result<String> {
+        let mut finder = NonFiniteFinder(None);
+        visitor::walk("", data, &mut finder);
+        if let Some(path) = finder.0 {
+            anyhow::bail!("Cannot save non-finite float at '{path}'");
+        }
+        let content = toml::to_string(data)?;
+        let Some(header) = &self.header else {
+            return Ok(content);
+        };
+        let mut out = String::new();
+        for line in header.lines() {
+            if line.is_empty() {
+                out.push_str("#\n");
+            } else {
+                out.push_str(&format!("# {line}\n"));
+            }
+        }
+        out.push('\n');
+        out.push_str(&content);
+        Ok(out)
+    }
+
+    /// Checks whether the configuration has a file path to save to.
+    ///
+    /// Configurations built in memory, such as those returned by
+    /// [`extract`](Self::extract), have no path and must be written with
+    /// [`save_as`](Self::save_as). A configuration from
+    /// [`load_optional`](Self::load_optional) has a path even if the file
+    /// does not exist yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_has_path.toml");
+    /// # std::fs::write(&path, "[database]\nhost = \"localhost\"\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert!(config.has_path());
+    /// assert!(!config.extract("database").unwrap().has_path());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn has_path(&self) -> bool {
+        !self.path.as_os_str().is_empty()
+    }
+
+    /// Checks whether the file on disk was modified since it was last read or written.
+    ///
+    /// The file's modification time is recorded by [`load`](Self::load),
+    /// [`load_buffered`](Self::load_buffered) and
+    /// [`reload_merge`](Self::reload_merge), and refreshed by every method
+    /// that writes the configuration's own file: [`save`](Self::save),
+    /// [`save_checked`](Self::save_checked), [`save_sorted`](Self::save_sorted),
+    /// [`save_with_backup`](Self::save_with_backup),
+    /// [`save_verified`](Self::save_verified), [`touch`](Self::touch) and
+    /// [`save_as`](Self::save_as) when given the same file. Only writes by
+    /// other processes make the file stale. Configurations that were neither
+    /// loaded from nor saved to a file are never stale.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file's metadata cannot be read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// if config.is_stale()? {
+    ///     println!("config.toml was edited externally");
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_is_stale.toml");
+    /// # std::fs::write(&path, "[server]\nport = 8080\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.set("server.port", 9090)?.save()?;
+    /// assert!(!config.is_stale()?);
+    /// config.save_checked()?;
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn is_stale(&self) -> Result<bool> {
+        let recorded = *self.modified.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(modified) = recorded else {
+            return Ok(false);
+        };
+        Ok(std::fs::metadata(&self.path)?.modified()? != modified)
+    }
+
+    /// Saves the configuration, refusing to overwrite external changes.
+    ///
+    /// On success the recorded modification time is updated, so repeated
+    /// calls keep working.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The file was modified on disk since it was loaded
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - The file cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 8080)?;
+    /// config.save_checked()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save_checked(&mut self) -> Result<()> {
+        if self.is_stale()? {
+            anyhow::bail!(
+                "'{}' was modified on disk since it was loaded",
+                self.path.display()
+            );
+        }
+        self.save()
+    }
+
+    /// Writes `content` to the configuration's own file and records the new
+    /// modification time, so our own writes never make the file look stale.
+    fn write_file(&self, content: &[u8]) -> Result<()> {
+        std::fs::write(&self.path, content)?;
+        self.record_modified(std::fs::metadata(&self.path)?.modified()?);
+        Ok(())
+    }
+
+    fn record_modified(&self, modified: SystemTime) {
+        *self.modified.lock().unwrap_or_else(PoisonError::into_inner) = Some(modified);
+    }
+
+    /// Serializes the configuration with all table keys sorted alphabetically.
+    ///
+    /// The output is the same as [`save`](Self::save) would write, except that
+    /// keys are ordered deterministically regardless of insertion order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// println!("{}", config.to_string_sorted()?);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn to_string_sorted(&self) -> Result<String> {
+        let mut data = self.saved_data().into_owned();
+        sort_tables(&mut data);
+        self.render_value(&data)
+    }
+
+    /// Saves the configuration with all table keys sorted alphabetically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - The file cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 8080)?;
+    /// config.save_sorted()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save_sorted(&self) -> Result<()> {
+        let content = self.to_string_sorted()?;
+        self.write_file(content.as_bytes())
+    }
+
+    /// Returns the contents [`save`](Self::save) would write, without touching disk.
+    ///
+    /// The output goes through the same rendering as `save`, including the
+    /// file header and pruning on save, so the preview is exact.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 8080)?;
+    /// println!("{}", config.preview_save()?);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn preview_save(&self) -> Result<String> {
+        self.render()
+    }
+
+    /// Saves the configuration, first copying the existing file to a backup.
+    ///
+    /// The backup is written next to the file, with `suffix` appended to its
+    /// name (e.g. `config.toml.bak`). If the file does not exist yet, no
+    /// backup is made.
+    ///
+    /// # Arguments
+    ///
+    /// * `suffix` - Suffix appended to the file name for the backup
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - The backup cannot be written
+    /// - The file cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.port", 8080)?;
+    /// config.save_with_backup(".bak")?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save_with_backup(&self, suffix: &str) -> Result<()> {
+        let content = self.render()?;
+        if self.path.try_exists()? {
+            let mut backup = self.path.clone().into_os_string();
+            backup.push(suffix);
+            std::fs::copy(&self.path, backup)?;
+        }
+        self.write_file(content.as_bytes())
+    }
+
+    /// Saves the configuration after checking that the output parses back identically.
+    ///
+    /// The serialized output is parsed again and compared to the in-memory
+    /// data; the file is only written if the round-trip is faithful.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - The serialized output does not parse back to the same data
+    /// - The file cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.set("server.timeout", 1.5)?;
+    /// config.save_verified()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save_verified(&self) -> Result<()> {
+        let data = self.saved_data();
+        let content = self.render_value(&data)?;
+        let reparsed: Value = toml::from_str(&content)?;
+        if reparsed != *data {
+            anyhow::bail!("Serialized configuration does not parse back to the same data");
+        }
+        self.write_file(content.as_bytes())
+    }
+
+    /// Saves the current configuration to a different file.
+    ///
+    /// The configuration's own path is left unchanged. If `path` refers to
+    /// the configuration's own file, the write is recorded as with
+    /// [`save`](Self::save), so [`is_stale`](Self::is_stale) stays accurate.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to write
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - The file cannot be written
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// config.save_as("config.backup.toml")?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save_as(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut content = Vec::new();
+        self.write_to(&mut content)?;
+        let own_file = self.has_path()
+            && std::fs::canonicalize(&self.path).ok() == std::fs::canonicalize(path).ok()
+            && path.try_exists()?;
+        if own_file {
+            return self.write_file(&content);
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Writes the configuration to any writer, such as a socket or a buffer.
+    ///
+    /// The output is exactly what [`save`](Self::save) would write. The whole
+    /// document is serialized before anything is written, so a serialization
+    /// error leaves the writer untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the TOML document
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The configuration cannot be serialized to TOML
+    /// - A float value is NaN or infinite
+    /// - Writing fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::from_reader("[server]\nport = 8080\n".as_bytes())?;
+    /// let mut buffer = Vec::new();
+    /// config.write_to(&mut buffer)?;
+    /// assert_eq!(String::from_utf8(buffer)?, "[server]\nport = 8080\n");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(self.render()?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Creates a new key-value pair, creating intermediate tables as needed.
+    ///
+    /// Unlike [`set`](Self::set), this method will create any missing parent tables.
+    /// When the parent is an array, the index one past the last element
+    /// appends a new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `value` - Value to set (must be convertible to `toml::Value`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key has an empty segment (e.g. `""`, `"a..b"` or `".a"`)
+    /// - A non-table value exists in the path where a table is needed
+    /// - The parent is an array and the last segment is past its end
+    /// - The key, or a key above or below it, is locked with [`lock_key`](Self::lock_key)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.create("new.nested.key", "value")?;
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// ```
+    /// # use tomlreadwr::{KeyPath, TomlConfig};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_create_array_element.toml");
+    /// # std::fs::write(&path, "nodes = [\"ns=2;s=Temp\"]\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.create(KeyPath::new().key("nodes").index(1), "ns=2;s=Speed")?;
+    /// assert_eq!(config.get_str("nodes.1"), Some("ns=2;s=Speed"));
+    /// assert!(config.create("nodes.5", "ns=2;s=Level").is_err());
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn create<T: Into<Value>>(&mut self, key: impl AsRef<str>, value: T) -> Result<&mut Self> {
+        let key = key.as_ref();
+        self.check_writable(key)?;
+        insert_creating(self.data_mut(), key, value.into())?;
+        self.notify(key, ChangeKind::Create);
+        Ok(self)
+    }
+
+    /// Sets a value until the returned guard is dropped.
+    ///
+    /// Missing parent tables are created as with [`create`](Self::create).
+    /// When the guard goes out of scope, the previous value is restored, or
+    /// the key is deleted if it did not exist before. The restore happens even
+    /// if the key was locked while the guard was alive, and `on_change` hooks
+    /// see it as a [`ChangeKind::Create`] or [`ChangeKind::Delete`].
+    ///
+    /// A restore fails only if a table on the key's path was replaced by a
+    /// non-table value through the guard. On drop, that panics in debug
+    /// builds and is ignored in release builds, leaving the configuration
+    /// modified; call [`ScopedSetGuard::restore`] to get the error instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `value` - Temporary value (must be convertible to `toml::Value`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`create`](Self::create).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_scoped_set.toml");
+    /// # std::fs::write(&path, "[source]\ninterval = 10\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// {
+    ///     let mut guard = config.scoped_set("source.interval", 1)?;
+    ///     assert_eq!(guard.get("source.interval").and_then(|v| v.as_integer()), Some(1));
+    ///     guard.lock_key("source.interval");
+    /// }
+    /// assert_eq!(config.get("source.interval").and_then(|v| v.as_integer()), Some(10));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// Hooks see both the temporary write and the restore:
+    ///
+    /// ```
+    /// # use tomlreadwr::{ChangeKind, TomlConfig};
+    /// # use std::sync::{Arc, Mutex};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_scoped_set_hooks.toml");
+    /// # std::fs::write(&path, "[source]\ninterval = 10\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// let seen = Arc::new(Mutex::new(Vec::new()));
+    /// let log = Arc::clone(&seen);
+    /// config.on_change(move |key, kind, value| {
+    ///     log.lock().unwrap().push((key.to_string(), kind, value.cloned()));
+    /// });
+    /// drop(config.scoped_set("source.interval", 1)?);
+    /// drop(config.scoped_set("source.retries", 3)?);
+    /// let seen = seen.lock().unwrap();
+    /// assert_eq!(seen[1], ("source.interval".to_string(), ChangeKind::Create, Some(10.into())));
+    /// assert_eq!(seen[3], ("source.retries".to_string(), ChangeKind::Delete, None));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn scoped_set<T: Into<Value>>(
+        &mut self,
+        key: &str,
+        value: T,
+    ) -> Result<ScopedSetGuard<'_>> {
+        let previous = resolve(&self.data, key).cloned();
+        self.create(key, value)?;
+        Ok(ScopedSetGuard::new(self, key, previous))
+    }
+
+    /// Puts back the value a [`ScopedSetGuard`] replaced, or removes the key
+    /// if it had none. The guard is undoing its own write, so locks and the
+    /// write policy are bypassed; change hooks are still notified.
+    pub(crate) fn restore(&mut self, key: &str, previous: Option<Value>) -> Result<()> {
+        let kind = match previous {
+            Some(previous) => {
+                insert_creating(self.data_mut(), key, previous)?;
+                ChangeKind::Create
+            }
+            None => {
+                if remove(self.data_mut(), key)?.is_none() {
+                    return Ok(());
+                }
+                ChangeKind::Delete
+            }
+        };
+        self.notify(key, kind);
+        Ok(())
+    }
+
+    /// Serializes a value to a table and appends it to an array of tables.
+    ///
+    /// The array is created, along with any missing parent tables, if it does
+    /// not exist yet. In the file this is a `[[key]]` entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the array
+    /// * `value` - Value implementing `Serialize` that serializes to a table
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `value` cannot be serialized, or does not serialize to a table
+    /// - The key exists but is not an array
+    /// - The key, or a key above or below it, is locked with [`lock_key`](Self::lock_key)
+    /// - The array has to be created and [`create`](Self::create) fails
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Machine {
+    ///     name: String,
+    ///     host: String,
+    /// }
+    ///
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// let machine = Machine { name: "press3".into(), host: "10.0.0.3".into() };
+    /// config.push_table("sources.machines", &machine)?.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn push_table<T: serde::Serialize>(&mut self, key: &str, value: &T) -> Result<&mut Self> {
+        self.check_writable(key)?;
+        let table = Value::try_from(value)?;
+        if !table.is_table() {
+            anyhow::bail!("Value for '{key}' does not serialize to a table");
+        }
+        match resolve_mut(self.data_mut(), key) {
+            Some(Value::Array(array)) => array.push(table),
+            Some(_) => anyhow::bail!("'{key}' is not an array"),
+            None => return self.create(key, Value::Array(vec![table])),
+        }
+        self.notify(key, ChangeKind::Set);
+        Ok(self)
+    }
+
+    /// Pushes a new, empty overlay onto the overlay stack.
+    ///
+    /// Overlays hold temporary overrides: [`get`](Self::get) consults them
+    /// from the most recently pushed down to the oldest before falling back
+    /// to the loaded data, while [`save`](Self::save) ignores them entirely.
+    /// A key found in an overlay shadows the whole value at that path, so
+    /// reading a table returns the overlay's table rather than a merged view.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.push_overlay().set_overlay("server.port", 9090)?;
+    /// assert_eq!(config.get("server.port").and_then(|v| v.as_integer()), Some(9090));
+    /// config.pop_overlay();
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn push_overlay(&mut self) -> &mut Self {
+        self.clear_cache();
+        self.overlays.push(Value::Table(toml::map::Map::new()));
+        self
+    }
+
+    /// Sets a value in the topmost overlay, creating intermediate tables as needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to the value
+    /// * `value` - Value to set (must be convertible to `toml::Value`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - No overlay has been pushed
+    /// - A non-table value exists in the path where a table is needed
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.push_overlay().set_overlay("feature.new_ui", true)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_overlay<T: Into<Value>>(&mut self, key: &str, value: T) -> Result<&mut Self> {
+        self.clear_cache();
+        let overlay = self
+            .overlays
+            .last_mut()
+            .ok_or_else(|| anyhow::anyhow!("No overlay has been pushed"))?;
+        insert_creating(overlay, key, value.into())?;
+        Ok(self)
+    }
+
+    /// Removes the topmost overlay and all of its overrides.
+    ///
+    /// Does nothing if no overlay has been pushed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.push_overlay().set_overlay("server.port", 9090)?;
+    /// config.pop_overlay();
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn pop_overlay(&mut self) -> &mut Self {
+        self.clear_cache();
+        self.overlays.pop();
+        self
+    }
+
+    /// Locks a key so that later changes to it are refused.
+    ///
+    /// [`set`](Self::set), [`create`](Self::create), [`delete`](Self::delete),
+    /// [`rename_key`](Self::rename_key) and [`push_table`](Self::push_table)
+    /// fail with "key '...' is locked" when they would touch the locked key,
+    /// a key inside it, or a table containing it. Bulk operations such as
+    /// [`merge_with`](Self::merge_with) and overlays are not checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Dot-separated path to protect
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_lock_key.toml");
+    /// # std::fs::write(&path, "[deploy]\nmachine_prefix = \"plant1\"\nzone = 2\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.lock_key("deploy.machine_prefix");
+    /// let err = config.set("deploy.machine_prefix", "plant2").err().unwrap();
+    /// assert_eq!(err.to_string(), "key 'deploy.machine_prefix' is locked");
+    /// assert!(config.delete("deploy").is_err());
+    /// config.set("deploy.zone", 3)?;
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn lock_key(&mut self, key: &str) -> &mut Self {
+        self.locked.push(key_names(key));
+        self
+    }
+
+    /// Registers a callback invoked after every `set`, `create` and `delete`.
+    ///
+    /// The hook receives the key, the kind of change and the new value
+    /// (`None` for deletions). [`rename_key`](Self::rename_key) reports a
+    /// deletion of the old key followed by a creation of the new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Callback to invoke
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// config.on_change(|key, kind, value| {
+    ///     println!("audit: {kind:?} {key} = {value:?}");
+    /// });
+    /// config.set("server.port", 9090)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn on_change<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: FnMut(&str, ChangeKind, Option<&Value>) + Send + Sync + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Restricts which keys may be changed using a policy function.
+    ///
+    /// The methods checked by [`lock_key`](Self::lock_key) also call
+    /// `policy(key)` with the key they are about to change, and fail with
+    /// "write to '...' is not allowed by the write policy" when it returns
+    /// `false`. [`rename_key`](Self::rename_key) checks both the old and the new
+    /// key. Setting a policy replaces the previous one; bulk operations such as
+    /// [`merge_with`](Self::merge_with) and overlays are not checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Returns whether writes to a key are allowed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_write_policy.toml");
+    /// # std::fs::write(&path, "[sources.opcua_machine1]\nport = 4840\n[sources.opcua_machine2]\nport = 4841\n")?;
+    /// let mut config = TomlConfig::load(&path)?;
+    /// config.set_write_policy(|key| key.starts_with("sources.opcua_machine1."));
+    /// config.set("sources.opcua_machine1.port", 4850)?;
+    /// let err = config.set("sources.opcua_machine2.port", 4850).err().unwrap();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "write to 'sources.opcua_machine2.port' is not allowed by the write policy"
+    /// );
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn set_write_policy<F>(&mut self, policy: F) -> &mut Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.write_policy = Some(Box::new(policy));
+        self
+    }
+
+    fn check_writable(&self, key: &str) -> Result<()> {
+        let names = key_names(key);
+        for locked in &self.locked {
+            let shared = names.len().min(locked.len());
+            if names[..shared] == locked[..shared] {
+                let locked: Vec<_> = locked.iter().map(|name| quote_key(name)).collect();
+                anyhow::bail!("key '{}' is locked", locked.join("."));
+            }
+        }
+        if let Some(policy) = &self.write_policy
+            && !policy(key)
+        {
+            anyhow::bail!("write to '{key}' is not allowed by the write policy");
+        }
+        Ok(())
+    }
+
+    fn notify(&mut self, key: &str, kind: ChangeKind) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let value = match kind {
+            ChangeKind::Delete => None,
+            ChangeKind::Set | ChangeKind::Create => resolve(&self.data, key),
+        };
+        for hook in &mut self.hooks {
+            hook(key, kind, value);
+        }
+    }
+
+    /// Flattens the configuration into environment variable assignments.
+    ///
+    /// Each scalar becomes one `(name, value)` pair whose name is `prefix`
+    /// followed by the path segments, all joined by `separator`. Array items
+    /// are addressed by index. Strings are exported as-is and other scalars in
+    /// their TOML form; key case is preserved.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Leading name segment; an empty prefix is omitted
+    /// * `separator` - Text placed between name segments, e.g. `"__"`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_to_env_vars.toml");
+    /// # std::fs::write(&path, "[sources.opcua_machine1]\nhost = \"10.0.0.1\"\nnodes = [1, 2]\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// let vars = config.to_env_vars("APP", "__");
+    /// assert_eq!(vars[0], ("APP__sources__opcua_machine1__host".to_string(), "10.0.0.1".to_string()));
+    /// assert_eq!(vars[2], ("APP__sources__opcua_machine1__nodes__1".to_string(), "2".to_string()));
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn to_env_vars(&self, prefix: &str, separator: &str) -> Vec<(String, String)> {
+        let mut vars = Vec::new();
+        flatten_env(prefix, &self.data, separator, &mut vars);
+        vars
+    }
+
+    /// Renders the configuration as an indented tree, for reading rather than parsing.
+    ///
+    /// Tables and arrays are shown as branches, with table keys sorted and
+    /// array elements labelled by index. Scalars are shown inline after their
+    /// key in TOML syntax, truncated to 40 characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_tree_string.toml");
+    /// # std::fs::write(&path, "[server]\nport = 8080\n\n[sources.opcua_machine1]\nhost = \"10.0.0.1\"\nnodes = [1, 2]\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// assert_eq!(
+    ///     config.tree_string(),
+    ///     "\
+    /// ├─ server
+    /// │  └─ port = 8080
+    /// └─ sources
+    ///    └─ opcua_machine1
+    ///       ├─ host = \"10.0.0.1\"
+    ///       └─ nodes
+    ///          ├─ [0] = 1
+    ///          └─ [1] = 2
+    /// "
+    /// );
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn tree_string(&self) -> String {
+        let mut out = String::new();
+        write_tree(&self.data, "", &mut out);
+        out
+    }
+
+    /// Returns every scalar value together with its dotted path, for mutation in place.
+    ///
+    /// Arrays are walked by index, so paths match the ones reported by
+    /// [`visit`](Self::visit) (e.g. `"servers.0.port"`). Changes made through
+    /// the references are not reported to [`on_change`](Self::on_change) hooks.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let mut config = TomlConfig::load("config.toml")?;
+    /// for (path, value) in config.iter_flat_mut() {
+    ///     if path.ends_with(".collection_interval_seconds") {
+    ///         if let Some(seconds) = value.as_integer() {
+    ///             *value = (seconds + 1).into();
+    ///         }
+    ///     }
+    /// }
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn iter_flat_mut(&mut self) -> impl Iterator<Item = (String, &mut Value)> {
+        let mut leaves = Vec::new();
+        collect_leaves_mut(String::new(), self.data_mut(), &mut leaves);
+        leaves.into_iter()
+    }
+
+    /// Returns every path in the configuration together with the kind of its value.
+    ///
+    /// Tables and arrays are listed before their contents, and array elements
+    /// are addressed by index. Table keys are visited in sorted order, so the
+    /// output only depends on the data and can be compared against a snapshot.
+    /// The root table is not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::{TomlConfig, ValueKind};
+    /// # let path = std::env::temp_dir().join("tomlreadwr_shape_iter.toml");
+    /// # std::fs::write(&path, "[server]\nport = 8080\ntags = [\"a\"]\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// let shape: Vec<_> = config.shape_iter().collect();
+    /// assert_eq!(
+    ///     shape,
+    ///     vec![
+    ///         ("server".to_string(), ValueKind::Table),
+    ///         ("server.port".to_string(), ValueKind::Integer),
+    ///         ("server.tags".to_string(), ValueKind::Array),
+    ///         ("server.tags.0".to_string(), ValueKind::String),
+    ///     ]
+    /// );
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn shape_iter(&self) -> impl Iterator<Item = (String, ValueKind)> {
+        let mut data = self.data.clone();
+        sort_tables(&mut data);
+        let mut collector = ShapeCollector(Vec::new());
+        visitor::walk("", &data, &mut collector);
+        collector.0.into_iter().filter(|(path, _)| !path.is_empty())
+    }
+
+    /// Returns every string value together with its dotted path.
+    ///
+    /// Arrays are walked by index, so paths match the ones reported by
+    /// [`visit`](Self::visit) (e.g. `"servers.0.url"`). The strings borrow from
+    /// the configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tomlreadwr::TomlConfig;
+    /// # let path = std::env::temp_dir().join("tomlreadwr_all_strings.toml");
+    /// # std::fs::write(&path, "[server]\nport = 8080\nurl = \"http://10.0.0.1\"\ntags = [\"edge\"]\n")?;
+    /// let config = TomlConfig::load(&path)?;
+    /// let insecure: Vec<_> = config
+    ///     .all_strings()
+    ///     .into_iter()
+    ///     .filter(|(_, s)| s.starts_with("http://"))
+    ///     .collect();
+    /// assert_eq!(insecure, vec![("server.url".to_string(), "http://10.0.0.1")]);
+    /// assert_eq!(config.all_strings().len(), 2);
+    /// # std::fs::remove_file(&path)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn all_strings(&self) -> Vec<(String, &str)> {
+        let mut strings = Vec::new();
+        collect_strings(String::new(), &self.data, &mut strings);
+        strings
+    }
+
+    /// Walks the whole configuration tree with the given visitor.
+    ///
+    /// Tables and arrays are visited depth-first, with scalars reported
+    /// through [`ConfigVisitor::visit_scalar`].
+    ///
+    /// # Arguments
+    ///
+    /// * `visitor` - Visitor receiving the traversal events
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::{ConfigVisitor, TomlConfig};
+    /// struct Counter(usize);
+    ///
+    /// impl ConfigVisitor for Counter {
+    ///     fn visit_scalar(&mut self, _path: &str, _value: &toml::Value) {
+    ///         self.0 += 1;
+    ///     }
+    /// }
+    ///
+    /// let config = TomlConfig::load("config.toml")?;
+    /// let mut counter = Counter(0);
+    /// config.visit(&mut counter);
+    /// println!("{} values", counter.0);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn visit<V: ConfigVisitor>(&self, visitor: &mut V) {
+        visitor::walk("", &self.data, visitor);
+    }
+
+    /// Returns the path to the configuration file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// println!("Config path: {:?}", config.get_path());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Returns a reference to the underlying TOML data.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use tomlreadwr::TomlConfig;
+    /// let config = TomlConfig::load("config.toml")?;
+    /// println!("Raw data: {:?}", config.get_data());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn get_data(&self) -> &Value {
+        &self.data
+    }
+}
+
+/// Byte ranges of every value in a parsed source, arranged like the values.
+struct SpanTree {
+    span: Range<usize>,
+    children: Option<SpanChildren>,
+}
+
+enum SpanChildren {
+    Table(HashMap<String, SpanTree>),
+    Array(Vec<SpanTree>),
+}
+
+impl SpanTree {
+    fn new(value: &DeValue<'_>, span: Range<usize>) -> Self {
+        let children = match value {
+            DeValue::Table(table) => Some(SpanChildren::Table(
+                table
+                    .iter()
+                    .map(|(key, value)| {
+                        let tree = SpanTree::new(value.get_ref(), value.span());
+                        (key.get_ref().to_string(), tree)
+                    })
+                    .collect(),
+            )),
+            DeValue::Array(array) => Some(SpanChildren::Array(
+                array
+                    .iter()
+                    .map(|value| SpanTree::new(value.get_ref(), value.span()))
+                    .collect(),
+            )),
+            _ => None,
+        };
+        SpanTree { span, children }
+    }
+}
+
+struct NonFiniteFinder(Option<String>);
+
+impl ConfigVisitor for NonFiniteFinder {
+    fn visit_scalar(&mut self, path: &str, value: &Value) {
+        if self.0.is_none() && value.as_float().is_some_and(|f| !f.is_finite()) {
+            self.0 = Some(path.to_string());
+        }
+    }
+}
+
+struct ShapeCollector(Vec<(String, ValueKind)>);
+
+impl ConfigVisitor for ShapeCollector {
+    fn visit_scalar(&mut self, path: &str, value: &Value) {
+        self.0.push((path.to_string(), ValueKind::of(value)));
+    }
+
+    fn enter_table(&mut self, path: &str) {
+        self.0.push((path.to_string(), ValueKind::Table));
+    }
+
+    fn enter_array(&mut self, path: &str) {
+        self.0.push((path.to_string(), ValueKind::Array));
+    }
+}
+
+/// Matches a file name against a pattern where `*` matches any run of
+/// characters and `?` matches exactly one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn parse_source(path: &Path, source: &str) -> Result<Value> {
+    toml::from_str(source).map_err(|e| {
+        let context = match e.span() {
+            Some(span) => {
+                let (line, column) = line_column(source, span.start);
+                format!(
+                    "failed to parse {} at line {line} column {column}",
+                    path.display()
+                )
+            }
+            None => format!("failed to parse {}", path.display()),
+        };
+        anyhow::Error::new(e).context(context)
+    })
+}
+
+/// Returns the 1-based line and column of a byte offset into `source`.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+pub(crate) fn join_key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+fn key_names(key: &str) -> Vec<String> {
+    split_key(key)
+        .map(|segments| {
+            segments
+                .into_iter()
+                .map(|segment| segment.name.into_owned())
+                .collect()
+        })
+        .unwrap_or_else(|_| vec![key.to_string()])
+}
+
+fn resolve<'a>(root: &'a Value, key: &str) -> Option<&'a Value> {
+    let mut current = root;
+    for part in split_key(key).ok()? {
+        current = child(current, &part)?;
+    }
+    Some(current)
+}
+
+fn resolve_mut<'a>(root: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    let mut current = root;
+    for part in split_key(key).ok()? {
+        current = child_mut(current, &part)?;
+    }
+    Some(current)
+}
+
+fn insert_existing(root: &mut Value, key: &str, value: Value) -> Result<()> {
+    let parts = split_key(key)?;
+
+    let mut current = root;
+
+    for part in &parts[..parts.len() - 1] {
+        current = child_mut(current, part)
+            .ok_or_else(|| anyhow::anyhow!("Path '{part}' does not exist"))?;
+        if !current.is_table() && !current.is_array() {
+            anyhow::bail!("'{part}' is not a table");
+        }
+    }
+
+    insert_child(current, &parts[parts.len() - 1], value, false)
+}
+
+fn insert_creating(root: &mut Value, key: &str, value: Value) -> Result<()> {
+    let parts = split_key(key)?;
+
+    let mut current = root;
+
+    for part in &parts[..parts.len() - 1] {
+        if child(current, part).is_none() {
+            current
+                .as_table_mut()
+                .ok_or_else(|| anyhow::anyhow!("Cannot create nested key in non-table"))?
+                .insert(part.name.to_string(), Value::Table(toml::map::Map::new()));
+        }
+
+        current = child_mut(current, part)
+            .ok_or_else(|| anyhow::anyhow!("Failed to navigate to '{}'", part))?;
+
+        if !current.is_table() && !current.is_array() {
+            anyhow::bail!("'{}' is not a table, cannot create nested keys", part);
+        }
+    }
+
+    insert_child(current, &parts[parts.len() - 1], value, true)
+}
+
+/// Stores `value` under the last segment of a key.
+///
+/// Inside an array the segment must be an index of an existing element,
+/// which is replaced; with `append`, the index one past the end pushes a
+/// new element instead.
+fn insert_child(parent: &mut Value, last: &Segment, value: Value, append: bool) -> Result<()> {
+    match parent {
+        Value::Table(table) => {
+            table.insert(last.name.to_string(), value);
+        }
+        Value::Array(array) => {
+            let index = last
+                .index()
+                .ok_or_else(|| anyhow::anyhow!("'{last}' is not an array index"))?;
+            let len = array.len();
+            match array.get_mut(index) {
+                Some(slot) => *slot = value,
+                None if append && index == len => array.push(value),
+                None => {
+                    anyhow::bail!("Index {index} is out of bounds for an array of length {len}")
+                }
+            }
+        }
+        _ => anyhow::bail!("Parent is not a table"),
+    }
+    Ok(())
+}
+
+fn remove(root: &mut Value, key: &str) -> Result<Option<Value>> {
+    let parts = split_key(key)?;
+
+    let mut current = root;
+
+    for part in &parts[..parts.len() - 1] {
+        current = child_mut(current, part)
+            .ok_or_else(|| anyhow::anyhow!("Path '{part}' does not exist"))?;
+        if !current.is_table() && !current.is_array() {
+            anyhow::bail!("'{part}' is not a table");
+        }
+    }
+
+    let last = &parts[parts.len() - 1];
+
+    match current {
+        Value::Table(table) => Ok(table.remove(last.name.as_ref())),
+        Value::Array(array) => {
+            let index = last
+                .index()
+                .ok_or_else(|| anyhow::anyhow!("'{last}' is not an array index"))?;
+            Ok((index < array.len()).then(|| array.remove(index)))
+        }
+        _ => anyhow::bail!("Parent is not a table"),
+    }
+}
+
+fn collect_changes<'a>(
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&'a Value>,
+    changes: &mut Vec<(String, Option<&'a Value>)>,
+) {
+    match (old, new) {
+        (Some(Value::Table(old)), Some(Value::Table(new))) => {
+            for (key, old_value) in old {
+                collect_changes(&join_key(path, key), Some(old_value), new.get(key), changes);
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    changes.push((join_key(path, key), Some(new_value)));
+                }
+            }
+        }
+        (old, new) if old != new => changes.push((path.to_string(), new)),
+        _ => {}
+    }
+}
+
+fn normalize_keys(value: &mut Value, style: KeyCase, path: &str) -> Result<()> {
+    match value {
+        Value::Table(table) => {
+            let old = std::mem::replace(table, toml::map::Map::new());
+            let mut sources: HashMap<String, String> = HashMap::new();
+            for (key, mut child) in old {
+                let new_key = style.convert(&key);
+                let child_path = join_key(path, &new_key);
+                if let Some(first) = sources.get(&new_key) {
+                    anyhow::bail!(
+                        "Keys '{}' and '{}' both become '{child_path}'",
+                        join_key(path, first),
+                        join_key(path, &key)
+                    );
+                }
+                normalize_keys(&mut child, style, &child_path)?;
+                table.insert(new_key.clone(), child);
+                sources.insert(new_key, key);
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                normalize_keys(item, style, &join_key(path, &index.to_string()))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn minimize(value: &Value, defaults: &Value) -> Option<Value> {
+    match (value, defaults) {
+        (Value::Table(table), Value::Table(defaults)) => {
+            let mut kept = toml::map::Map::new();
+            for (key, child) in table {
+                let child = match defaults.get(key) {
+                    Some(default) => minimize(child, default),
+                    None => Some(child.clone()),
+                };
+                if let Some(child) = child {
+                    kept.insert(key.clone(), child);
+                }
+            }
+            (!kept.is_empty()).then_some(Value::Table(kept))
+        }
+        (value, defaults) => (value != defaults).then(|| value.clone()),
+    }
+}
+
+fn collect_config_changes(
+    path: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<ConfigChange>,
+) {
+    match (old, new) {
+        (Some(Value::Table(old)), Some(Value::Table(new))) => {
+            for (key, old_value) in old {
+                collect_config_changes(
+                    &join_key(path, key),
+                    Some(old_value),
+                    new.get(key),
+                    changes,
+                );
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    collect_config_changes(&join_key(path, key), None, Some(new_value), changes);
+                }
+            }
+        }
+        (Some(old), Some(new)) if old != new => changes.push(ConfigChange::Changed {
+            key: path.to_string(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        (Some(old), None) => changes.push(ConfigChange::Removed {
+            key: path.to_string(),
+            value: old.clone(),
+        }),
+        (None, Some(new)) => changes.push(ConfigChange::Added {
+            key: path.to_string(),
+            value: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn collect_type_diffs(
+    path: &str,
+    a: &Value,
+    b: &Value,
+    diffs: &mut Vec<(String, ValueKind, ValueKind)>,
+) {
+    match (a, b) {
+        (Value::Table(a), Value::Table(b)) => {
+            for (key, a_value) in a {
+                if let Some(b_value) = b.get(key) {
+                    collect_type_diffs(&join_key(path, key), a_value, b_value, diffs);
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (index, (a_item, b_item)) in a.iter().zip(b).enumerate() {
+                collect_type_diffs(&join_key(path, &index.to_string()), a_item, b_item, diffs);
+            }
+        }
+        _ if ValueKind::of(a) != ValueKind::of(b) => {
+            diffs.push((path.to_string(), ValueKind::of(a), ValueKind::of(b)));
+        }
+        _ => {}
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn interpolate_env(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) => *s = expand_env(s)?,
+        Value::Array(array) => array.iter_mut().try_for_each(interpolate_env)?,
+        Value::Table(table) => table
+            .iter_mut()
+            .try_for_each(|(_, child)| interpolate_env(child))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn expand_env(input: &str) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("Unclosed '${{' in '{input}'"))?;
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+            out.push_str(&env_var(name, default)?);
+            rest = &after[end + 1..];
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                out.push('$');
+            } else {
+                out.push_str(&env_var(&rest[..end], None)?);
+                rest = &rest[end..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn env_var(name: &str, default: Option<&str>) -> Result<String> {
+    match (std::env::var(name), default) {
+        (Ok(value), _) => Ok(value),
+        (Err(_), Some(default)) => Ok(default.to_string()),
+        (Err(_), None) => anyhow::bail!("Environment variable '{name}' is not set"),
+    }
+}
+
+fn sort_tables(value: &mut Value) {
+    match value {
+        Value::Table(table) => {
+            let mut entries: Vec<(String, Value)> = std::mem::take(table).into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, mut child) in entries {
+                sort_tables(&mut child);
+                table.insert(key, child);
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(sort_tables),
+        _ => {}
+    }
+}
+
+fn format_floats(value: &mut Value, format: FloatFormat) {
+    match value {
+        Value::Float(f) => *f = format.apply(*f),
+        Value::Array(items) => items.iter_mut().for_each(|v| format_floats(v, format)),
+        Value::Table(table) => table.iter_mut().for_each(|(_, v)| format_floats(v, format)),
+        _ => {}
+    }
+}
+
+fn prune_empty_tables(value: &mut Value) {
+    if let Value::Table(table) = value {
+        for (_, child) in table.iter_mut() {
+            prune_empty_tables(child);
+        }
+        table.retain(|_, child| !child.as_table().is_some_and(|t| t.is_empty()));
+    }
+}
+
+fn merge_resolving<F: Fn(&str, &Value, &Value) -> Value>(
+    target: &mut Value,
+    other: &Value,
+    path: &str,
+    resolver: &F,
+) {
+    match (target, other) {
+        (Value::Table(target), Value::Table(other)) => {
+            for (key, value) in other {
+                let path = join_key(path, key);
+                match target.get_mut(key) {
+                    Some(existing) => merge_resolving(existing, value, &path, resolver),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, other) if *target != *other => *target = resolver(path, target, other),
+        _ => {}
+    }
+}
+
+fn fill_missing(target: &mut Value, defaults: &Value) {
+    if let (Value::Table(target), Value::Table(defaults)) = (target, defaults) {
+        for (key, value) in defaults {
+            match target.get_mut(key) {
+                Some(existing) => fill_missing(existing, value),
+                None => {
+                    target.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+}
+
+fn write_tree(value: &Value, indent: &str, out: &mut String) {
+    const MAX_WIDTH: usize = 40;
+
+    let children: Vec<(String, &Value)> = match value {
+        Value::Table(table) => {
+            let mut entries: Vec<(String, &Value)> =
+                table.iter().map(|(k, v)| (k.clone(), v)).collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            entries
+        }
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(index, v)| (format!("[{index}]"), v))
+            .collect(),
+        _ => return,
+    };
+    let count = children.len();
+    for (position, (label, child)) in children.into_iter().enumerate() {
+        let last = position + 1 == count;
+        out.push_str(indent);
+        out.push_str(if last { "└─ " } else { "├─ " });
+        out.push_str(&label);
+        if child.is_table() || child.is_array() {
+            out.push('\n');
+            let indent = format!("{indent}{}", if last { "   " } else { "│  " });
+            write_tree(child, &indent, out);
+        } else {
+            let text = child.to_string();
+            out.push_str(" = ");
+            if text.chars().count() > MAX_WIDTH {
+                out.extend(text.chars().take(MAX_WIDTH - 1));
+                out.push('…');
+            } else {
+                out.push_str(&text);
+            }
+            out.push('\n');
+        }
+    }
+}
+
+fn flatten_env(name: &str, value: &Value, separator: &str, out: &mut Vec<(String, String)>) {
+    let child_name = |key: &str| {
+        if name.is_empty() {
+            key.to_string()
+        } else {
+            format!("{name}{separator}{key}")
+        }
+    };
+    match value {
+        Value::Table(table) => {
+            for (key, child) in table {
+                flatten_env(&child_name(key), child, separator, out);
+            }
+        }
+        Value::Array(array) => {
+            for (index, child) in array.iter().enumerate() {
+                flatten_env(&child_name(&index.to_string()), child, separator, out);
+            }
+        }
+        Value::String(s) => out.push((name.to_string(), s.clone())),
+        _ => out.push((name.to_string(), value.to_string())),
+    }
+}
+
+fn collect_by_suffix<'a>(
+    path: &str,
+    value: &'a Value,
+    suffix: &str,
+    matches: &mut Vec<(String, &'a Value)>,
+) {
+    match value {
+        Value::Table(table) => {
+            for (key, child) in table {
+                let child_path = join_key(path, key);
+                if key == suffix {
+                    matches.push((child_path, child));
+                } else {
+                    collect_by_suffix(&child_path, child, suffix, matches);
+                }
+            }
+        }
+        Value::Array(array) => {
+            for (index, child) in array.iter().enumerate() {
+                collect_by_suffix(&join_key(path, &index.to_string()), child, suffix, matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_strings<'a>(path: String, value: &'a Value, out: &mut Vec<(String, &'a str)>) {
+    match value {
+        Value::Table(table) => {
+            for (key, child) in table {
+                collect_strings(join_key(&path, key), child, out);
+            }
+        }
+        Value::Array(array) => {
+            for (index, child) in array.iter().enumerate() {
+                collect_strings(join_key(&path, &index.to_string()), child, out);
+            }
+        }
+        Value::String(s) => out.push((path, s)),
+        _ => {}
+    }
+}
+
+fn collect_leaves_mut<'a>(
+    path: String,
+    value: &'a mut Value,
+    out: &mut Vec<(String, &'a mut Value)>,
+) {
+    match value {
+        Value::Table(table) => {
+            for (key, child) in table.iter_mut() {
+                collect_leaves_mut(join_key(&path, key), child, out);
+            }
+        }
+        Value::Array(array) => {
+            for (index, child) in array.iter_mut().enumerate() {
+                collect_leaves_mut(join_key(&path, &index.to_string()), child, out);
+            }
+        }
+        _ => out.push((path, value)),
+    }
+}
+
+fn child<'a>(value: &'a Value, segment: &Segment) -> Option<&'a Value> {
+    match value {
+        Value::Array(array) => array.get(segment.index()?),
+        _ => value.get(segment.name.as_ref()),
+    }
+}
+
+fn child_mut<'a>(value: &'a mut Value, segment: &Segment) -> Option<&'a mut Value> {
+    match value {
+        Value::Array(array) => array.get_mut(segment.index()?),
+        _ => value.get_mut(segment.name.as_ref()),
+    }
+}
+
+fn deep_merge(target: &mut Value, overlay: &Value) {
+    match (target, overlay) {
+        (Value::Table(target), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match target.get_mut(key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, overlay) => *target = overlay.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_backtracks_over_stars() {
+        assert!(glob_match("*.toml", "base.toml"));
+        assert!(glob_match("*-*.toml", "site-a-east.toml"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**a", "ba"));
+        assert!(!glob_match("*.toml", "base.toml.bak"));
+        assert!(!glob_match("a*b", "acbc"));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn glob_match_question_mark_matches_one_char() {
+        assert!(glob_match("site?.toml", "site1.toml"));
+        assert!(glob_match("?é", "né"));
+        assert!(!glob_match("site?.toml", "site.toml"));
+        assert!(!glob_match("site?.toml", "site12.toml"));
+    }
+
+    #[test]
+    fn edit_distance_counts_single_char_edits() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("port", "port"), 0);
+        assert_eq!(edit_distance("", "port"), 4);
+        assert_eq!(edit_distance("port", ""), 4);
+        assert_eq!(edit_distance("prot", "port"), 2);
+        assert_eq!(edit_distance("hots", "host"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("timeout", "timeouts"), 1);
+        assert_eq!(edit_distance("ñame", "name"), 1);
+    }
+
+    #[test]
+    fn expand_env_handles_escapes_and_defaults() {
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(expand_env("no variables").unwrap(), "no variables");
+        assert_eq!(expand_env("cost: $$5").unwrap(), "cost: $5");
+        assert_eq!(expand_env("$$PATH").unwrap(), "$PATH");
+        assert_eq!(expand_env("trailing $").unwrap(), "trailing $");
+        assert_eq!(expand_env("$ alone").unwrap(), "$ alone");
+        assert_eq!(expand_env("${PATH}").unwrap(), path);
+        assert_eq!(expand_env("$PATH/bin").unwrap(), format!("{path}/bin"));
+        assert_eq!(
+            expand_env("${TOMLREADWR_TEST_UNSET:-opc.tcp://localhost}").unwrap(),
+            "opc.tcp://localhost"
+        );
+        assert_eq!(expand_env("${TOMLREADWR_TEST_UNSET:-}").unwrap(), "");
+        assert_eq!(expand_env("${PATH:-fallback}").unwrap(), path);
+    }
+
+    #[test]
+    fn expand_env_rejects_unclosed_and_unset() {
+        let err = expand_env("host = ${HOST").unwrap_err();
+        assert_eq!(err.to_string(), "Unclosed '${' in 'host = ${HOST'");
+        let err = expand_env("$TOMLREADWR_TEST_UNSET").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Environment variable 'TOMLREADWR_TEST_UNSET' is not set"
+        );
+        assert!(expand_env("${TOMLREADWR_TEST_UNSET}").is_err());
+    }
+
+    #[test]
+    fn interpolate_env_walks_arrays_and_tables() {
+        let mut value: Value =
+            toml::from_str("a = \"$$x\"\nb = [\"$$y\", 1]\n[c]\nd = \"$$z\"\n").unwrap();
+        interpolate_env(&mut value).unwrap();
+        let expected: Value =
+            toml::from_str("a = \"$x\"\nb = [\"$y\", 1]\n[c]\nd = \"$z\"\n").unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn normalize_keys_renames_nested_keys() {
+        let mut value: Value =
+            toml::from_str("[serverConfig]\nmaxConn = 1\n[[nodeList]]\nnodeId = \"a\"\n").unwrap();
+        normalize_keys(&mut value, KeyCase::SnakeCase, "").unwrap();
+        let expected: Value =
+            toml::from_str("[server_config]\nmax_conn = 1\n[[node_list]]\nnode_id = \"a\"\n")
+                .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn normalize_keys_reports_collisions() {
+        let mut value: Value = toml::from_str("[server]\nmax_conn = 1\nmaxConn = 2\n").unwrap();
+        let err = normalize_keys(&mut value, KeyCase::SnakeCase, "").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Keys 'server.maxConn' and 'server.max_conn' both become 'server.max_conn'"
+        );
+
+        let mut value: Value = toml::from_str("[[nodes]]\nnodeId = 1\nnode_id = 2\n").unwrap();
+        let err = normalize_keys(&mut value, KeyCase::SnakeCase, "").unwrap_err();
+        assert!(err.to_string().ends_with("both become 'nodes.0.node_id'"));
     }
 }
@@ -0,0 +1,55 @@
+use toml::Value;
+
+/// The kind of a TOML value, without its contents.
+///
+/// # Examples
+///
+/// ```
+/// # use tomlreadwr::ValueKind;
+/// let value = toml::Value::from(vec!["a", "b"]);
+/// assert_eq!(ValueKind::of(&value), ValueKind::Array);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Datetime,
+    Array,
+    Table,
+}
+
+impl ValueKind {
+    /// Returns the kind of the given value.
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::String(_) => ValueKind::String,
+            Value::Integer(_) => ValueKind::Integer,
+            Value::Float(_) => ValueKind::Float,
+            Value::Boolean(_) => ValueKind::Boolean,
+            Value::Datetime(_) => ValueKind::Datetime,
+            Value::Array(_) => ValueKind::Array,
+            Value::Table(_) => ValueKind::Table,
+        }
+    }
+}
+
+impl From<&Value> for ValueKind {
+    fn from(value: &Value) -> Self {
+        ValueKind::of(value)
+    }
+}
+
+/// Whether an array is absent, empty, or holds elements.
+///
+/// Returned by [`TomlConfig::array_state`](crate::TomlConfig::array_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArrayState {
+    /// The key does not exist or does not hold an array.
+    Missing,
+    /// The key holds an empty array.
+    Empty,
+    /// The key holds an array with the given number of elements.
+    NonEmpty(usize),
+}
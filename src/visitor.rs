@@ -0,0 +1,71 @@
+use toml::Value;
+
+use crate::toml_config::join_key;
+
+/// A read-only visitor over the configuration tree.
+///
+/// All methods have empty default implementations, so a visitor only needs to
+/// override the events it cares about. Paths use the same dot notation as
+/// [`TomlConfig::get`](crate::TomlConfig::get), with array elements addressed
+/// by their index. The root table has the empty path `""`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use tomlreadwr::{ConfigVisitor, TomlConfig};
+/// use toml::Value;
+///
+/// #[derive(Default)]
+/// struct StringCollector {
+///     strings: Vec<String>,
+/// }
+///
+/// impl ConfigVisitor for StringCollector {
+///     fn visit_scalar(&mut self, path: &str, value: &Value) {
+///         if let Some(s) = value.as_str() {
+///             self.strings.push(format!("{path} = {s}"));
+///         }
+///     }
+/// }
+///
+/// let config = TomlConfig::load("config.toml")?;
+/// let mut collector = StringCollector::default();
+/// config.visit(&mut collector);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub trait ConfigVisitor {
+    /// Called for every value that is neither a table nor an array.
+    fn visit_scalar(&mut self, _path: &str, _value: &Value) {}
+
+    /// Called before the entries of a table are visited.
+    fn enter_table(&mut self, _path: &str) {}
+
+    /// Called after all entries of a table have been visited.
+    fn exit_table(&mut self, _path: &str) {}
+
+    /// Called before the elements of an array are visited.
+    fn enter_array(&mut self, _path: &str) {}
+
+    /// Called after all elements of an array have been visited.
+    fn exit_array(&mut self, _path: &str) {}
+}
+
+pub(crate) fn walk<V: ConfigVisitor + ?Sized>(path: &str, value: &Value, visitor: &mut V) {
+    match value {
+        Value::Table(table) => {
+            visitor.enter_table(path);
+            for (key, child) in table {
+                walk(&join_key(path, key), child, visitor);
+            }
+            visitor.exit_table(path);
+        }
+        Value::Array(array) => {
+            visitor.enter_array(path);
+            for (index, child) in array.iter().enumerate() {
+                walk(&join_key(path, &index.to_string()), child, visitor);
+            }
+            visitor.exit_array(path);
+        }
+        _ => visitor.visit_scalar(path, value),
+    }
+}